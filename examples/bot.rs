@@ -1,7 +1,5 @@
 // This bot throws a dice on each incoming message.
 
-use teloxide::prelude::*;
-use teloxide::RequestError;
 use teloxide_core::requests::JsonRequest;
 use teloxide::payloads;
 use teloxide_core::payloads::GetUpdates;
@@ -11,17 +9,14 @@ type UpdatesManager = JsonRequest<payloads::GetUpdates>;
 
 #[tokio::main]
 async fn main() {
-    use teloxide_core::{
-        prelude::*,
-        types::{DiceEmoji, ParseMode},
-    };
+    use teloxide_core::prelude::*;
 
     let bot = Bot::new("");
     let mut get_updates = GetUpdates::new();
     loop {
         println!("{:?}", get_updates);
         let updates = UpdatesManager::new(bot.clone(), get_updates.clone()).await.unwrap();
-        if updates.len() > 0 {
+        if !updates.is_empty() {
             let last_update = updates.last().unwrap();
             get_updates = GetUpdates {
                 offset: Some(last_update.id),
@@ -1,51 +1,293 @@
-use std::io::{Read, Write};
-use age;
+use std::fmt;
+use std::fs;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
 use age::secrecy::Secret;
-use bincode;
+use age::{ssh, x25519};
 
-use crate::encryption::Encryptor;
-use crate::models::Folder;
+use crate::encryption::{EncryptionError, Encryptor};
+use crate::models::Vault;
 
+/// Encrypts vaults with age, either with a shared passphrase or to the public keys of
+/// everyone who may open the vault: the keys of the identities it was opened with,
+/// the recipients it was given and the members stored in the vault.
+///
+/// The keys of the identities and the given recipients are stored as members when a
+/// vault is encrypted, so that whoever saves it next keeps encrypting it to them.
+#[derive(Clone)]
 pub struct AgeEncryptor {
-    key: String,
+    key: AgeKey,
+}
+
+#[derive(Clone)]
+enum AgeKey {
+    Passphrase(String),
+    Identities {
+        identities: Vec<AgeIdentity>,
+        recipients: Vec<AgeRecipient>,
+    },
+}
+
+#[derive(Clone)]
+enum AgeIdentity {
+    X25519(x25519::Identity),
+    Ssh(ssh::Identity),
+}
+
+/// Public key a vault can be encrypted to: an age X25519 key or an SSH key.
+#[derive(Clone)]
+pub enum AgeRecipient {
+    X25519(x25519::Recipient),
+    Ssh(ssh::Recipient),
+}
+
+impl fmt::Display for AgeRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgeRecipient::X25519(recipient) => recipient.fmt(f),
+            AgeRecipient::Ssh(recipient) => recipient.fmt(f),
+        }
+    }
+}
+
+impl From<x25519::Recipient> for AgeRecipient {
+    fn from(recipient: x25519::Recipient) -> Self {
+        AgeRecipient::X25519(recipient)
+    }
+}
+
+impl AgeRecipient {
+    fn boxed(&self) -> Box<dyn age::Recipient + Send> {
+        match self {
+            AgeRecipient::X25519(recipient) => Box::new(recipient.clone()),
+            AgeRecipient::Ssh(recipient) => Box::new(recipient.clone()),
+        }
+    }
+}
+
+impl AgeIdentity {
+    fn as_identity(&self) -> &dyn age::Identity {
+        match self {
+            AgeIdentity::X25519(identity) => identity,
+            AgeIdentity::Ssh(identity) => identity,
+        }
+    }
 }
 
 impl AgeEncryptor {
+    /// Passphrase mode.
     pub fn new(key: String) -> Self {
-        Self { key }
+        Self { key: AgeKey::Passphrase(key) }
+    }
+
+    /// Encrypts to `recipients` and the public keys of `identities`, and decrypts with
+    /// any of `identities`.
+    pub fn with_identities(identities: Vec<x25519::Identity>, recipients: Vec<AgeRecipient>) -> Self {
+        let own_keys = identities.iter().map(|identity| identity.to_public().into());
+        let recipients = own_keys.chain(recipients).collect();
+        let identities = identities.into_iter().map(AgeIdentity::X25519).collect();
+        Self { key: AgeKey::Identities { identities, recipients: dedup(recipients) } }
+    }
+
+    /// Reads the identities of an age identity file, as written by `age-keygen`.
+    pub fn from_identity_file(path: &Path, recipients: Vec<AgeRecipient>) -> Result<Self, EncryptionError> {
+        let file = age::IdentityFile::from_file(path.to_string_lossy().into_owned())
+            .map_err(EncryptionError::IdentityFileError)?;
+        let identities: Vec<x25519::Identity> = file.into_identities()
+            .into_iter()
+            .map(|entry| match entry {
+                age::IdentityFileEntry::Native(identity) => identity,
+            })
+            .collect();
+        if identities.is_empty() {
+            return Err(EncryptionError::NoIdentities);
+        }
+        Ok(Self::with_identities(identities, recipients))
+    }
+
+    /// Reads an OpenSSH `ssh-ed25519` or `ssh-rsa` private key such as `~/.ssh/id_ed25519`,
+    /// decrypting it with `passphrase` if it is protected. The vault is encrypted to the
+    /// public key stored in the private key file.
+    pub fn from_ssh_key(path: &Path, passphrase: Option<String>) -> Result<Self, EncryptionError> {
+        let file = fs::File::open(path).map_err(EncryptionError::IdentityFileError)?;
+        let filename = path.to_string_lossy().into_owned();
+        let identity = ssh::Identity::from_buffer(BufReader::new(file), Some(filename))
+            .map_err(EncryptionError::IdentityFileError)?;
+        let identity = match identity {
+            ssh::Identity::Encrypted(key) => {
+                let passphrase = passphrase.ok_or(EncryptionError::KeyPassphraseRequired)?;
+                let key = key.decrypt(Secret::new(passphrase))
+                    .map_err(|_| EncryptionError::WrongKeyPassphrase)?;
+                ssh::Identity::Unencrypted(key)
+            }
+            ssh::Identity::Unsupported(_) => return Err(EncryptionError::UnsupportedKey),
+            identity => identity,
+        };
+        let recipient = ssh::Recipient::try_from(identity.clone())
+            .map_err(|_| EncryptionError::UnsupportedKey)?;
+        let recipients = vec![AgeRecipient::Ssh(recipient)];
+        Ok(Self {
+            key: AgeKey::Identities { identities: vec![AgeIdentity::Ssh(identity)], recipients },
+        })
+    }
+
+    /// Reads an SSH private key or an age identity file, depending on what `path` holds.
+    /// `passphrase` is only used for protected SSH keys.
+    pub fn from_key_file(path: &Path, passphrase: Option<String>) -> Result<Self, EncryptionError> {
+        let contents = fs::read(path).map_err(EncryptionError::IdentityFileError)?;
+        if contents.starts_with(b"-----BEGIN") {
+            Self::from_ssh_key(path, passphrase)
+        } else {
+            Self::from_identity_file(path, Vec::new())
+        }
+    }
+
+    /// Public keys of the identities and the recipients given on creation, empty in
+    /// passphrase mode.
+    pub fn recipients(&self) -> &[AgeRecipient] {
+        match &self.key {
+            AgeKey::Passphrase(_) => &[],
+            AgeKey::Identities { recipients, .. } => recipients,
+        }
+    }
+
+    pub fn is_passphrase(&self) -> bool {
+        matches!(self.key, AgeKey::Passphrase(_))
+    }
+
+    /// Public keys `vault` is encrypted to: [`Self::recipients`] followed by the keys
+    /// of the vault members, without duplicates. Empty in passphrase mode.
+    pub fn recipients_for(&self, vault: &Vault) -> Result<Vec<AgeRecipient>, EncryptionError> {
+        if self.is_passphrase() {
+            return Ok(Vec::new());
+        }
+        let mut recipients = self.recipients().to_vec();
+        for member in vault.members() {
+            recipients.push(parse_recipient(&member.public_key)?);
+        }
+        Ok(dedup(recipients))
+    }
+}
+
+/// Label of the members added for the keys of the encryptor.
+const OWNER_LABEL: &str = "owner";
+
+/// Stores every key in `recipients` that is not a member of `vault` yet as a member.
+fn store_members(vault: &mut Vault, recipients: &[AgeRecipient]) {
+    for recipient in recipients {
+        // Fails for keys that already are members, which is fine.
+        let _ = vault.add_member(OWNER_LABEL, &recipient.to_string());
+    }
+}
+
+/// Number of public keys an encrypted vault was encrypted to, read from its age header.
+/// Zero for vaults encrypted with a passphrase.
+pub fn recipient_count(encrypted: &[u8]) -> usize {
+    encrypted
+        .split(|&byte| byte == b'\n')
+        .take_while(|line| !line.starts_with(b"---"))
+        .filter(|line| {
+            [&b"-> X25519 "[..], b"-> ssh-ed25519 ", b"-> ssh-rsa "]
+                .iter()
+                .any(|stanza| line.starts_with(stanza))
+        })
+        .count()
+}
+
+fn dedup(recipients: Vec<AgeRecipient>) -> Vec<AgeRecipient> {
+    let mut unique: Vec<AgeRecipient> = Vec::new();
+    for recipient in recipients {
+        if !unique.iter().any(|r| r.to_string() == recipient.to_string()) {
+            unique.push(recipient);
+        }
+    }
+    unique
+}
+
+impl fmt::Debug for AgeEncryptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key {
+            AgeKey::Passphrase(_) => f.write_str("AgeEncryptor(passphrase)"),
+            AgeKey::Identities { recipients, .. } => {
+                let recipients: Vec<String> = recipients.iter().map(|r| r.to_string()).collect();
+                f.debug_struct("AgeEncryptor").field("recipients", &recipients).finish()
+            }
+        }
+    }
+}
+
+/// Parses an age public key such as `age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p`,
+/// or an `ssh-ed25519` or `ssh-rsa` public key as found in `~/.ssh/id_*.pub`.
+pub fn parse_recipient(recipient: &str) -> Result<AgeRecipient, EncryptionError> {
+    let recipient = recipient.trim();
+    if recipient.starts_with("age1") {
+        return recipient
+            .parse()
+            .map(AgeRecipient::X25519)
+            .map_err(|_| EncryptionError::InvalidRecipient(recipient.to_string()));
+    }
+    match recipient.parse() {
+        Ok(recipient) => Ok(AgeRecipient::Ssh(recipient)),
+        Err(ssh::ParseRecipientKeyError::Invalid(_)) if recipient.starts_with("ssh-") => {
+            Err(EncryptionError::InvalidRecipient(recipient.to_string()))
+        }
+        Err(_) => Err(EncryptionError::UnsupportedRecipient(recipient.to_string())),
     }
 }
 
 impl Encryptor for AgeEncryptor {
-    fn encrypt(&self, data: &mut Folder) -> Vec<u8> {
-        let bytes_data = bincode::serialize(&data).unwrap();
-        let encryptor = age::Encryptor::with_user_passphrase(Secret::new(self.key.clone()));
+    fn encrypt(&self, data: &mut Vault) -> Result<Vec<u8>, EncryptionError> {
+        store_members(data, self.recipients());
+        let bytes_data = data.encode().map_err(EncryptionError::Serialization)?;
+        let encryptor = match &self.key {
+            AgeKey::Passphrase(key) => age::Encryptor::with_user_passphrase(Secret::new(key.clone())),
+            AgeKey::Identities { .. } => {
+                let recipients = self.recipients_for(data)?.iter().map(AgeRecipient::boxed).collect();
+                age::Encryptor::with_recipients(recipients).ok_or(EncryptionError::NoRecipients)?
+            }
+        };
         let mut encrypted = vec![];
-        let mut writer = encryptor.wrap_output(&mut encrypted).unwrap();
-        writer.write_all(&bytes_data).unwrap();
-        writer.finish().unwrap();
-        encrypted
+        let mut writer = encryptor.wrap_output(&mut encrypted)?;
+        writer.write_all(&bytes_data)?;
+        writer.finish()?;
+        Ok(encrypted)
     }
 
-    fn decrypt(&self, data: Vec<u8>) -> Folder {
-        let decryptor = match age::Decryptor::new(&data[..]).unwrap() {
-            age::Decryptor::Passphrase(d) => d,
-            _ => unreachable!()
+    /// Decrypts with the passphrase or the identities, depending on which kind of
+    /// age header the vault has.
+    fn decrypt(&self, data: Vec<u8>) -> Result<Vault, EncryptionError> {
+        let mut reader = match (age::Decryptor::new(&data[..])?, &self.key) {
+            (age::Decryptor::Passphrase(d), AgeKey::Passphrase(key)) => {
+                d.decrypt(&Secret::new(key.clone()), None)?
+            }
+            (age::Decryptor::Recipients(d), AgeKey::Identities { identities, .. }) => {
+                d.decrypt(identities.iter().map(AgeIdentity::as_identity))?
+            }
+            (age::Decryptor::Passphrase(_), _) => return Err(EncryptionError::PassphraseRequired),
+            (age::Decryptor::Recipients(_), _) => return Err(EncryptionError::IdentityRequired),
         };
         let mut decrypted = Vec::new();
-        let mut reader = decryptor.decrypt(&Secret::new(self.key.clone()), None).unwrap();
-        reader.read_to_end(&mut decrypted).unwrap();
-        let mut folder: Folder = bincode::deserialize(&decrypted).unwrap();
-        folder
+        reader.read_to_end(&mut decrypted).map_err(|_| EncryptionError::CorruptData)?;
+        Vault::decode(&decrypted).map_err(EncryptionError::Deserialization)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Write;
     use std::path::Path;
-    use crate::encryption::age_encryptor::AgeEncryptor;
-    use crate::encryption::Encryptor;
-    use crate::models::{Folder, Record};
+    use age::secrecy::{ExposeSecret, Secret};
+    use age::x25519;
+    use crate::encryption::age_encryptor::{parse_recipient, recipient_count, AgeEncryptor};
+    use crate::encryption::{EncryptionError, Encryptor};
+    use crate::models::{Folder, Record, Vault};
+
+    /// Test keys, `id_ed25519_protected` has the passphrase "correct horse".
+    const SSH_KEYS: &str = "tests/fixtures/ssh/";
+
+    fn ssh_key(name: &str) -> std::path::PathBuf {
+        Path::new(SSH_KEYS).join(name)
+    }
 
     #[test]
     fn test_encryptor() {
@@ -53,13 +295,205 @@ mod tests {
         let mut record = Record::new();
         record.add_field("name".into(), "value".into()).unwrap();
         let path = Path::new("tests/testfile.test");
-        record.add_file(&path).unwrap();
-        let mut subfolder = Folder::new();
-        let mut main_folder = Folder::new();
+        record.add_file(path).unwrap();
+        let subfolder = Folder::new("subfolder".into());
+        let mut main_folder = Folder::new("main".into());
         main_folder.add_folder(subfolder);
         main_folder.add_record(record);
-        let key = String::from("key");
-        let encrypted = encryptor.encrypt(main_folder);
-        let decrypted = encryptor.decrypt(encrypted);
+        let mut vault = Vault::new(main_folder);
+        let encrypted = encryptor.encrypt(&mut vault).unwrap();
+        let decrypted = encryptor.decrypt(encrypted).unwrap();
+        assert_eq!(decrypted, vault);
+    }
+
+    #[test]
+    fn test_decrypt_errors() {
+        let mut vault = Vault::new(Folder::new("main".into()));
+        let mut encrypted = AgeEncryptor::new("key".into()).encrypt(&mut vault).unwrap();
+        let wrong_key = AgeEncryptor::new("other key".into());
+        assert!(matches!(wrong_key.decrypt(encrypted.clone()), Err(EncryptionError::WrongKey)));
+
+        let encryptor = AgeEncryptor::new("key".into());
+        assert!(matches!(encryptor.decrypt(b"not a vault".to_vec()), Err(EncryptionError::CorruptData)));
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(matches!(encryptor.decrypt(encrypted), Err(EncryptionError::CorruptData)));
+
+        let identity = x25519::Identity::generate();
+        let for_recipient = AgeEncryptor::with_identities(vec![identity], Vec::new())
+            .encrypt(&mut vault)
+            .unwrap();
+        assert!(matches!(encryptor.decrypt(for_recipient), Err(EncryptionError::IdentityRequired)));
+    }
+
+    #[test]
+    fn test_decrypt_invalid_contents() {
+        let encryptor = age::Encryptor::with_user_passphrase(Secret::new("key".to_string()));
+        let mut encrypted = vec![];
+        let mut writer = encryptor.wrap_output(&mut encrypted).unwrap();
+        writer.write_all(b"NRDS\x02\x00garbage").unwrap();
+        writer.finish().unwrap();
+        let result = AgeEncryptor::new("key".into()).decrypt(encrypted);
+        assert!(matches!(result, Err(EncryptionError::Deserialization(_))));
+    }
+
+    #[test]
+    fn test_encrypt_to_recipients() {
+        let alice = x25519::Identity::generate();
+        let bob = x25519::Identity::generate();
+        let mut vault = Vault::new(Folder::new("team".into()));
+        let encryptor = AgeEncryptor::with_identities(vec![alice.clone()], vec![bob.to_public().into()]);
+        assert_eq!(encryptor.recipients().len(), 2);
+        let encrypted = encryptor.encrypt(&mut vault).unwrap();
+
+        let as_bob = AgeEncryptor::with_identities(vec![bob], Vec::new());
+        assert_eq!(as_bob.decrypt(encrypted.clone()).unwrap(), vault);
+        let as_alice = AgeEncryptor::with_identities(vec![alice], Vec::new());
+        assert_eq!(as_alice.decrypt(encrypted.clone()).unwrap(), vault);
+
+        let stranger = AgeEncryptor::with_identities(vec![x25519::Identity::generate()], Vec::new());
+        assert!(matches!(stranger.decrypt(encrypted.clone()), Err(EncryptionError::WrongKey)));
+
+        // Opened without the recipients, saving still encrypts to them.
+        let mut opened = as_alice.decrypt(encrypted).unwrap();
+        assert_eq!(opened.members().len(), 2);
+        let encrypted = as_alice.encrypt(&mut opened).unwrap();
+        assert_eq!(as_bob.decrypt(encrypted).unwrap(), opened);
+
+        let with_passphrase = AgeEncryptor::new("key".into()).encrypt(&mut vault).unwrap();
+        assert!(matches!(stranger.decrypt(with_passphrase), Err(EncryptionError::PassphraseRequired)));
+    }
+
+    #[test]
+    fn test_identity_file() {
+        let identity = x25519::Identity::generate();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key.txt");
+        let contents = format!(
+            "# public key: {}\n{}\n",
+            identity.to_public(),
+            identity.to_string().expose_secret()
+        );
+        std::fs::write(&path, contents).unwrap();
+
+        let encryptor = AgeEncryptor::from_identity_file(&path, Vec::new()).unwrap();
+        assert_eq!(encryptor.recipients()[0].to_string(), identity.to_public().to_string());
+        let mut vault = Vault::new(Folder::new("team".into()));
+        let encrypted = encryptor.encrypt(&mut vault).unwrap();
+        assert_eq!(encryptor.decrypt(encrypted).unwrap(), vault);
+
+        let empty = dir.path().join("empty.txt");
+        std::fs::write(&empty, "# nothing here\n").unwrap();
+        assert!(matches!(AgeEncryptor::from_identity_file(&empty, Vec::new()), Err(EncryptionError::NoIdentities)));
+        let missing = dir.path().join("missing.txt");
+        assert!(matches!(
+            AgeEncryptor::from_identity_file(&missing, Vec::new()),
+            Err(EncryptionError::IdentityFileError(_))
+        ));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_recipient() {
+        let public = x25519::Identity::generate().to_public().to_string();
+        assert_eq!(parse_recipient(&format!(" {} ", public)).unwrap().to_string(), public);
+        assert!(matches!(parse_recipient("age1invalid"), Err(EncryptionError::InvalidRecipient(_))));
+        assert!(matches!(parse_recipient("ssh-ed25519 AAAA"), Err(EncryptionError::InvalidRecipient(_))));
+        assert!(matches!(parse_recipient("pgp AAAA"), Err(EncryptionError::UnsupportedRecipient(_))));
+        let ssh_key = std::fs::read_to_string(SSH_KEYS.to_owned() + "id_ed25519.pub").unwrap();
+        assert!(ssh_key.starts_with(&parse_recipient(&ssh_key).unwrap().to_string()));
+    }
+
+    #[test]
+    fn test_encrypt_to_members() {
+        let alice = x25519::Identity::generate();
+        let bob = x25519::Identity::generate();
+        let as_alice = AgeEncryptor::with_identities(vec![alice], Vec::new());
+        let as_bob = AgeEncryptor::with_identities(vec![bob.clone()], Vec::new());
+        let mut vault = Vault::new(Folder::new("team".into()));
+        vault.add_member("Bob", &bob.to_public().to_string()).unwrap();
+        assert_eq!(as_alice.recipients_for(&vault).unwrap().len(), 2);
+
+        let encrypted = as_alice.encrypt(&mut vault).unwrap();
+        let decrypted = as_bob.decrypt(encrypted).unwrap();
+        assert_eq!(decrypted.members(), vault.members());
+
+        vault.remove_member(&bob.to_public().to_string()).unwrap();
+        let encrypted = as_alice.encrypt(&mut vault).unwrap();
+        assert!(matches!(as_bob.decrypt(encrypted), Err(EncryptionError::WrongKey)));
+
+        vault.add_member("Carol", "pgp AAAA").unwrap();
+        assert!(matches!(as_alice.encrypt(&mut vault), Err(EncryptionError::UnsupportedRecipient(_))));
+        assert!(AgeEncryptor::new("key".into()).recipients_for(&vault).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_members_keep_the_owner() {
+        let alice = x25519::Identity::generate();
+        let bob = x25519::Identity::generate();
+        let as_alice = AgeEncryptor::with_identities(vec![alice.clone()], Vec::new());
+        let as_bob = AgeEncryptor::with_identities(vec![bob.clone()], Vec::new());
+        let mut vault = Vault::new(Folder::new("team".into()));
+        vault.add_member("Bob", &bob.to_public().to_string()).unwrap();
+        let encrypted = as_alice.encrypt(&mut vault).unwrap();
+        assert_eq!(vault.members()[1].public_key, alice.to_public().to_string());
+        assert_eq!(recipient_count(&encrypted), 2);
+
+        let mut opened_by_bob = as_bob.decrypt(encrypted).unwrap();
+        let encrypted = as_bob.encrypt(&mut opened_by_bob).unwrap();
+        assert_eq!(opened_by_bob.members().len(), 2);
+        assert_eq!(recipient_count(&encrypted), 2);
+        assert_eq!(as_alice.decrypt(encrypted).unwrap(), opened_by_bob);
+
+        let with_passphrase = AgeEncryptor::new("key".into()).encrypt(&mut vault).unwrap();
+        assert_eq!(recipient_count(&with_passphrase), 0);
+    }
+
+    #[test]
+    fn test_ssh_keys() {
+        let mut vault = Vault::new(Folder::new("team".into()));
+        let ed25519 = AgeEncryptor::from_ssh_key(&ssh_key("id_ed25519"), None).unwrap();
+        let rsa = AgeEncryptor::from_key_file(&ssh_key("id_rsa"), None).unwrap();
+        let rsa_public = std::fs::read_to_string(ssh_key("id_rsa.pub")).unwrap();
+        vault.add_member("rsa", &rsa_public).unwrap();
+        assert_eq!(ed25519.recipients_for(&vault).unwrap().len(), 2);
+
+        let encrypted = ed25519.encrypt(&mut vault).unwrap();
+        assert_eq!(rsa.decrypt(encrypted.clone()).unwrap(), vault);
+        assert_eq!(ed25519.decrypt(encrypted.clone()).unwrap(), vault);
+        let x25519 = AgeEncryptor::with_identities(vec![x25519::Identity::generate()], Vec::new());
+        assert!(matches!(x25519.decrypt(encrypted), Err(EncryptionError::WrongKey)));
+    }
+
+    #[test]
+    fn test_ssh_public_key_from_private_key() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["id_ed25519", "id_rsa"] {
+            let lone_key = dir.path().join(name);
+            std::fs::copy(ssh_key(name), &lone_key).unwrap();
+            let public = std::fs::read_to_string(ssh_key(&format!("{}.pub", name))).unwrap();
+            let encryptor = AgeEncryptor::from_ssh_key(&lone_key, None).unwrap();
+            assert!(public.starts_with(&encryptor.recipients()[0].to_string()));
+        }
+
+        let protected = AgeEncryptor::from_ssh_key(&ssh_key("id_ed25519_protected"), Some("correct horse".into()));
+        let public = std::fs::read_to_string(ssh_key("id_ed25519_protected.pub")).unwrap();
+        assert!(public.starts_with(&protected.unwrap().recipients()[0].to_string()));
+    }
+
+    #[test]
+    fn test_protected_ssh_key() {
+        let path = ssh_key("id_ed25519_protected");
+        assert!(matches!(
+            AgeEncryptor::from_key_file(&path, None),
+            Err(EncryptionError::KeyPassphraseRequired)
+        ));
+        assert!(matches!(
+            AgeEncryptor::from_ssh_key(&path, Some("wrong".into())),
+            Err(EncryptionError::WrongKeyPassphrase)
+        ));
+        let encryptor = AgeEncryptor::from_ssh_key(&path, Some("correct horse".into())).unwrap();
+        let mut vault = Vault::new(Folder::new("team".into()));
+        let encrypted = encryptor.encrypt(&mut vault).unwrap();
+        assert_eq!(encryptor.decrypt(encrypted).unwrap(), vault);
+    }
+}
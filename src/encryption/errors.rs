@@ -0,0 +1,61 @@
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("Wrong passphrase or key")]
+    WrongKey,
+
+    #[error("Vault data is corrupted")]
+    CorruptData,
+
+    #[error("Vault is encrypted with a passphrase")]
+    PassphraseRequired,
+
+    #[error("Vault is encrypted to public keys, an identity file is needed to open it")]
+    IdentityRequired,
+
+    #[error("Unsupported recipient type {0}")]
+    UnsupportedRecipient(String),
+
+    #[error("Invalid recipient {0}")]
+    InvalidRecipient(String),
+
+    #[error("Vault has no recipients")]
+    NoRecipients,
+
+    #[error("Error reading identity file")]
+    IdentityFileError(#[source] std::io::Error),
+
+    #[error("Identity file contains no keys")]
+    NoIdentities,
+
+    #[error("SSH key is protected by a passphrase")]
+    KeyPassphraseRequired,
+
+    #[error("Wrong passphrase for the SSH key")]
+    WrongKeyPassphrase,
+
+    #[error("Unsupported SSH key type")]
+    UnsupportedKey,
+
+    #[error("Vault contents could not be read")]
+    Deserialization(#[source] bincode::Error),
+
+    #[error("Vault contents could not be written")]
+    Serialization(#[source] bincode::Error),
+
+    #[error("Error encrypting vault")]
+    EncryptionFailed(#[from] age::EncryptError),
+
+    #[error("Error writing encrypted vault")]
+    WriteError(#[from] std::io::Error),
+}
+
+impl From<age::DecryptError> for EncryptionError {
+    fn from(error: age::DecryptError) -> Self {
+        match error {
+            age::DecryptError::DecryptionFailed
+            | age::DecryptError::KeyDecryptionFailed
+            | age::DecryptError::NoMatchingKeys => EncryptionError::WrongKey,
+            _ => EncryptionError::CorruptData,
+        }
+    }
+}
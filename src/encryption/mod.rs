@@ -1,10 +1,12 @@
 mod age_encryptor;
+mod errors;
 
-use crate::models::Folder;
+use crate::models::Vault;
 
-pub use age_encryptor::AgeEncryptor;
+pub use age_encryptor::{parse_recipient, recipient_count, AgeEncryptor, AgeRecipient};
+pub use errors::EncryptionError;
 
 pub trait Encryptor {
-    fn encrypt(&self, data: &mut Folder) -> Vec<u8>;
-    fn decrypt(&self, data: Vec<u8>) -> Folder;
-}
\ No newline at end of file
+    fn encrypt(&self, data: &mut Vault) -> Result<Vec<u8>, EncryptionError>;
+    fn decrypt(&self, data: Vec<u8>) -> Result<Vault, EncryptionError>;
+}
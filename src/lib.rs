@@ -0,0 +1,4 @@
+pub mod encryption;
+pub mod models;
+pub mod storage;
+pub mod sync;
@@ -1,69 +1,175 @@
-use std::collections::HashMap;
-use iced::{Application, Command, Element, Renderer, Settings, Theme};
-use iced::widget::{button, row, text, text_input, column, Column};
+use std::collections::{BTreeSet, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, Utc};
+use iced::{theme, Application, Color, Command, Element, Length, Renderer, Settings, Theme};
+use iced::widget::{button, checkbox, mouse_area, pick_list, row, text, text_input, column, Column};
 use home::home_dir;
-use iced::futures::StreamExt;
+use indexmap::IndexMap;
+use uuid::Uuid;
 
-mod models;
-mod encryption;
-mod storage;
-
-use models::Folder;
-use encryption::AgeEncryptor;
-use storage::LocalStorageManager;
-use crate::models::Record;
-use crate::storage::StorageManager;
+use nordstone::models::{Folder, Vault};
+use nordstone::encryption::{parse_recipient, AgeEncryptor, EncryptionError};
+use nordstone::storage::LocalStorageManager;
+use nordstone::models::{
+    FieldKind, FieldValue, ModelsError, Record, RecordFilter, RecordPath, RecordTemplate, RecordVersion,
+    TrashEntry, TrashedItem,
+};
+use nordstone::storage::{StorageError, StorageManager};
 
 #[derive(Debug)]
 struct NordstoneUi {
     state: MainState,
-    subfolder_to_edit: Option<usize>,
-    record_to_edit: Option<usize>,
-    key: Option<String>,
+    subfolder_to_edit: Option<Uuid>,
+    encryptor: Option<AgeEncryptor>,
     records: Vec<RecordUi>,
+    search: String,
+    selected_tags: BTreeSet<String>,
+    favorites_only: bool,
+    dragging: Option<TreeItem>,
+    tree_error: Option<String>,
+    storage_error: Option<String>,
+    show_trash: bool,
+    show_members: bool,
+    member_label: String,
+    member_key: String,
+    trash_retention: String,
+    templates: Vec<String>,
+    /// Number of public keys the vault file is encrypted to. Saving it to fewer keys is
+    /// refused unless members were removed or the user confirms.
+    encrypted_to: usize,
+    confirm_dropping_keys: bool,
 }
 
 impl NordstoneUi {
-    fn decrypt(&mut self, key: String) {
-        self.key = Some(key.clone());
-        let encryptor = AgeEncryptor::new(key);
-        let config_path = home_dir().unwrap().join("nordstone.cfg");
-        if config_path.exists() {
-            let storage_manager = LocalStorageManager::new(
-                config_path, encryptor,
-            );
-            let data = storage_manager.load();
-            self.state = MainState::Decrypted(data);
-            return;
+    /// Opens the vault with `encryptor`, or shows why it could not be opened and keeps
+    /// the decrypt form for another try.
+    fn decrypt(&mut self, encryptor: Result<AgeEncryptor, EncryptionError>) {
+        let result = encryptor
+            .map_err(StorageError::from)
+            .and_then(|encryptor| Ok((open_vault(&encryptor)?, encryptor)));
+        let (mut data, encryptor) = match result {
+            Ok(data) => data,
+            Err(error) => {
+                if let MainState::Encrypted(ref mut form) = self.state {
+                    form.show_error(&error);
+                }
+                return;
+            }
+        };
+        self.encrypted_to = vault_storage(encryptor.clone())
+            .and_then(|storage_manager| storage_manager.recipient_count())
+            .unwrap_or(0);
+        self.encryptor = Some(encryptor);
+        self.trash_retention = data.trash().retention_days().to_string();
+        self.templates = template_names(&data);
+        let purged = data.purge_trash(Utc::now());
+        self.state = MainState::Decrypted(data);
+        if purged > 0 {
+            self.encrypt();
         }
-        self.state = MainState::Decrypted(Folder::new("NEW FOLDER".into()))
     }
 
+    /// Saves the vault, showing a banner if it could not be written. A save that would
+    /// no longer encrypt the vault to every key it is encrypted to now is refused until
+    /// the user confirms it, and one with a member key that cannot be used is refused.
     fn encrypt(&mut self) {
-        let encryptor = AgeEncryptor::new(self.key.clone().unwrap());
-        let config_path = home_dir().unwrap().join("nordstone.cfg");
-        let storage_manager = LocalStorageManager::new(
-            config_path, encryptor,
-        );
-        if let MainState::Decrypted(ref mut data) = self.state {
-            storage_manager.save(data)
+        let MainState::Decrypted(ref mut data) = self.state else {
+            return;
+        };
+        let encryptor = self.encryptor.clone().unwrap();
+        let recipients = match encryptor.recipients_for(data) {
+            Ok(recipients) => recipients.len(),
+            Err(error) => {
+                self.storage_error = Some(error.to_string());
+                self.confirm_dropping_keys = false;
+                return;
+            }
+        };
+        if recipients < self.encrypted_to {
+            self.storage_error = Some(format!(
+                "the vault is encrypted to {} keys but only {} of them are members, saving would lock the others out",
+                self.encrypted_to,
+                recipients
+            ));
+            self.confirm_dropping_keys = true;
+            return;
+        }
+        let result = vault_storage(encryptor.clone())
+            .and_then(|storage_manager| storage_manager.save(data));
+        if result.is_ok() {
+            self.encrypted_to = recipients;
+        }
+        self.storage_error = result.err().map(|error| error.to_string());
+        self.confirm_dropping_keys = false;
+    }
+
+    /// Editors for every record of `folder` plus an empty one for a new record.
+    fn records_ui(folder: &Folder) -> Vec<RecordUi> {
+        let mut records: Vec<RecordUi> = folder.records.iter().map(|r| {
+            RecordUi::from_record(r)
+        }).collect();
+        records.push(RecordUi::new(Uuid::new_v4(), IndexMap::new()));
+        records
+    }
+
+    /// Rebuilds the record editors after the tree changed, closing the edited folder
+    /// if it no longer exists.
+    fn refresh_records(&mut self) {
+        let MainState::Decrypted(ref data) = self.state else {
+            return;
+        };
+        match self.subfolder_to_edit.and_then(|id| data.root.find_folder(id)) {
+            Some(folder) => self.records = Self::records_ui(folder),
+            None => {
+                self.subfolder_to_edit = None;
+                self.records.clear();
+            }
         }
     }
 }
 
+/// Record or folder being dragged in the tree view.
+#[derive(Debug, Clone, Copy)]
+enum TreeItem {
+    Record(Uuid),
+    Folder(Uuid),
+}
+
 #[derive(Debug)]
 enum MainState {
-    Decrypted(Folder),
+    Decrypted(Vault),
     Encrypted(DecryptForm),
 }
 
 #[derive(Debug, Clone)]
 enum MainMessage {
     DecryptFormMessage(DecryptFormMessage),
-    EditFolder(usize),
-    ChangeFolder((usize, String)),
+    EditFolder(Uuid),
+    ChangeFolder((Uuid, String)),
     Save,
-    RecordUiMessage((usize, RecordUiMessage)),
+    RecordUiMessage((Uuid, RecordUiMessage)),
+    Search(String),
+    ToggleTag(String),
+    ToggleFavorites,
+    Drag(TreeItem),
+    Drop(Uuid),
+    CancelDrag,
+    DeleteFolder(Uuid),
+    SortRecords(Uuid),
+    ToggleTrash,
+    RestoreFromTrash(usize),
+    DeleteFromTrash(usize),
+    EmptyTrash,
+    EditTrashRetention(String),
+    SetTrashRetention,
+    DismissStorageError,
+    SaveDroppingKeys,
+    ToggleMembers,
+    EditMemberLabel(String),
+    EditMemberKey(String),
+    AddMember,
+    RemoveMember(String),
 }
 
 impl Application for NordstoneUi {
@@ -72,19 +178,28 @@ impl Application for NordstoneUi {
     type Theme = Theme;
     type Flags = ();
 
-    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
+    fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
         let form = DecryptForm::new();
         (
             Self {
                 state: MainState::Encrypted(form),
                 subfolder_to_edit: None,
-                record_to_edit: None,
-                key: None,
-                records: vec![RecordUi {
-                    state: RecordUiState::Edit(HashMap::new()),
-                    key_to_add: "".to_string(),
-                    value_to_add: "".to_string(),
-                }],
+                encryptor: None,
+                records: Vec::new(),
+                search: String::new(),
+                selected_tags: BTreeSet::new(),
+                favorites_only: false,
+                dragging: None,
+                tree_error: None,
+                storage_error: None,
+                show_trash: false,
+                show_members: false,
+                member_label: String::new(),
+                member_key: String::new(),
+                trash_retention: String::new(),
+                templates: Vec::new(),
+                encrypted_to: 0,
+                confirm_dropping_keys: false,
             },
             Command::none()
         )
@@ -100,12 +215,13 @@ impl Application for NordstoneUi {
                 match message {
                     MainMessage::DecryptFormMessage(msg) => {
                         match msg {
-                            DecryptFormMessage::KeyChanged(_) => {
-                                form.update(msg.clone());
+                            DecryptFormMessage::Decrypt => {
+                                let encryptor = form.encryptor();
+                                self.decrypt(encryptor);
                                 Command::none()
                             }
-                            DecryptFormMessage::Decrypt(key) => {
-                                self.decrypt(key);
+                            msg => {
+                                form.update(msg);
                                 Command::none()
                             }
                         }
@@ -116,22 +232,134 @@ impl Application for NordstoneUi {
             MainState::Decrypted(ref mut data) => {
                 match message {
                     MainMessage::DecryptFormMessage(_) => { Command::none() }
-                    MainMessage::EditFolder(index) => {
-                        self.subfolder_to_edit = Some(index);
-                        if !data.records.is_empty() {
-                            self.records = data.records.iter().map(|r| {
-                                RecordUi { state: RecordUiState::Edit(r.fields.clone()), key_to_add: "".to_string(), value_to_add: "".to_string() }
-                            }).collect();
+                    MainMessage::EditFolder(id) => {
+                        if let Some(folder) = data.root.find_folder(id) {
+                            self.subfolder_to_edit = Some(id);
+                            self.records = Self::records_ui(folder);
+                        }
+                        Command::none()
+                    }
+                    MainMessage::ChangeFolder((id, new_name)) => {
+                        if let Some(folder) = data.root.find_folder_mut(id) {
+                            folder.rename(new_name)
+                        }
+                        Command::none()
+                    }
+                    MainMessage::Search(query) => {
+                        self.search = query;
+                        Command::none()
+                    }
+                    MainMessage::ToggleTag(tag) => {
+                        if !self.selected_tags.remove(&tag) {
+                            self.selected_tags.insert(tag);
+                        }
+                        Command::none()
+                    }
+                    MainMessage::ToggleFavorites => {
+                        self.favorites_only = !self.favorites_only;
+                        Command::none()
+                    }
+                    MainMessage::Drag(item) => {
+                        self.dragging = Some(item);
+                        self.tree_error = None;
+                        Command::none()
+                    }
+                    MainMessage::CancelDrag => {
+                        self.dragging = None;
+                        Command::none()
+                    }
+                    MainMessage::Drop(target) => {
+                        let result = match self.dragging.take() {
+                            Some(TreeItem::Record(id)) => data.root.move_record(id, target),
+                            Some(TreeItem::Folder(id)) => data.root.move_folder(id, target),
+                            None => return Command::none(),
+                        };
+                        self.tree_changed(result)
+                    }
+                    MainMessage::DeleteFolder(id) => {
+                        let result = data.trash_folder(id);
+                        self.tree_changed(result)
+                    }
+                    MainMessage::SortRecords(id) => {
+                        let result = data.root.find_folder_mut(id)
+                            .map(Folder::sort_records)
+                            .ok_or(ModelsError::FolderNotFound);
+                        self.tree_changed(result)
+                    }
+                    MainMessage::ToggleTrash => {
+                        self.show_trash = !self.show_trash;
+                        self.show_members = false;
+                        Command::none()
+                    }
+                    MainMessage::ToggleMembers => {
+                        self.show_members = !self.show_members;
+                        self.show_trash = false;
+                        Command::none()
+                    }
+                    MainMessage::EditMemberLabel(label) => {
+                        self.member_label = label;
+                        Command::none()
+                    }
+                    MainMessage::EditMemberKey(key) => {
+                        self.member_key = key;
+                        Command::none()
+                    }
+                    MainMessage::AddMember => {
+                        if let Err(error) = parse_recipient(&self.member_key) {
+                            self.tree_error = Some(error.to_string());
+                            return Command::none();
+                        }
+                        let result = data.add_member(&self.member_label, &self.member_key);
+                        if result.is_ok() {
+                            self.member_label.clear();
+                            self.member_key.clear();
+                        }
+                        self.tree_changed(result)
+                    }
+                    MainMessage::RemoveMember(public_key) => {
+                        let result = data.remove_member(&public_key).map(|_| ());
+                        if result.is_ok() {
+                            self.encrypted_to = self.encrypted_to.saturating_sub(1);
                         }
+                        self.tree_changed(result)
+                    }
+                    MainMessage::RestoreFromTrash(index) => {
+                        let result = data.restore_from_trash(index);
+                        self.tree_changed(result)
+                    }
+                    MainMessage::DeleteFromTrash(index) => {
+                        let result = data.delete_from_trash(index).map(|_| ());
+                        self.tree_changed(result)
+                    }
+                    MainMessage::EmptyTrash => {
+                        data.empty_trash();
+                        self.tree_changed(Ok(()))
+                    }
+                    MainMessage::EditTrashRetention(days) => {
+                        self.trash_retention = days;
                         Command::none()
                     }
-                    MainMessage::ChangeFolder((index, new_name)) => {
-                        match data.subfolders {
-                            Some(ref mut subs) => {
-                                subs[index].rename(new_name)
+                    MainMessage::SetTrashRetention => {
+                        match self.trash_retention.trim().parse() {
+                            Ok(days) => {
+                                data.set_trash_retention(days);
+                                data.purge_trash(Utc::now());
+                                self.tree_changed(Ok(()))
+                            }
+                            Err(_) => {
+                                self.tree_error = Some("Retention must be a number of days".into());
+                                Command::none()
                             }
-                            _ => {}
                         }
+                    }
+                    MainMessage::DismissStorageError => {
+                        self.storage_error = None;
+                        self.confirm_dropping_keys = false;
+                        Command::none()
+                    }
+                    MainMessage::SaveDroppingKeys => {
+                        self.encrypted_to = 0;
+                        self.encrypt();
                         Command::none()
                     }
                     MainMessage::Save => {
@@ -139,21 +367,163 @@ impl Application for NordstoneUi {
                         self.subfolder_to_edit = None;
                         Command::none()
                     }
-                    MainMessage::RecordUiMessage((index, msg)) => {
+                    MainMessage::RecordUiMessage((id, msg)) => {
                         match msg {
-                            RecordUiMessage::Save(fields) => {
-                                if data.records.is_empty() {
-                                    data.records.push(Record {
-                                        fields: fields.clone(),
-                                        files: None,
-                                    })
-                                } else { data.records[index].fields = fields.clone(); }
-
+                            RecordUiMessage::Save(drafts) => {
+                                let fields = match field_values(drafts) {
+                                    Ok(fields) => fields,
+                                    Err(error) => {
+                                        if let Some(record_ui) = self.records.iter_mut().find(|r| r.id == id) {
+                                            record_ui.error = Some(error.to_string());
+                                        }
+                                        return Command::none();
+                                    }
+                                };
+                                let Some(record_ui) = self.records.iter().find(|r| r.id == id) else {
+                                    return Command::none();
+                                };
+                                let is_new = data.root.find_record(id).is_none();
+                                let record_id = if is_new {
+                                    let Some(folder) = self.subfolder_to_edit
+                                        .and_then(|folder_id| data.root.find_folder_mut(folder_id)) else {
+                                        return Command::none();
+                                    };
+                                    let record = Record::new();
+                                    let record_id = record.id();
+                                    folder.add_record(record);
+                                    record_id
+                                } else {
+                                    id
+                                };
+                                if let Some(record) = data.root.find_record_mut(record_id) {
+                                    record.set_fields(fields);
+                                    record_ui.apply_details(record);
+                                }
+                                if is_new {
+                                    self.records.push(RecordUi::new(Uuid::new_v4(), IndexMap::new()));
+                                }
+                                let record = data.root.find_record(record_id);
+                                let record_ui = self.records.iter_mut().find(|r| r.id == id);
+                                if let (Some(record), Some(record_ui)) = (record, record_ui) {
+                                    *record_ui = RecordUi::from_record(record);
+                                }
+                                self.encrypt();
+                                Command::none()
+                            }
+                            RecordUiMessage::AttachFile => {
+                                let Some(record_ui) = self.records.iter_mut().find(|r| r.id == id) else {
+                                    return Command::none();
+                                };
+                                let Some(record) = data.root.find_record_mut(id) else {
+                                    return Command::none();
+                                };
+                                match record.add_file(Path::new(record_ui.file_to_attach.trim())) {
+                                    Ok(()) => {
+                                        record_ui.refresh_files(record);
+                                        record_ui.file_to_attach.clear();
+                                        self.encrypt();
+                                    }
+                                    Err(error) => record_ui.error = Some(error.to_string()),
+                                }
+                                Command::none()
+                            }
+                            RecordUiMessage::ExportFile(filename) => {
+                                let (Some(record_ui), Some(record)) = (
+                                    self.records.iter_mut().find(|r| r.id == id),
+                                    data.root.find_record(id),
+                                ) else {
+                                    return Command::none();
+                                };
+                                let directory = Path::new(record_ui.export_directory.trim());
+                                match record.extract_file(&filename, directory, false) {
+                                    Ok(path) => {
+                                        record_ui.status = Some(format!("exported to {}", path.display()));
+                                        record_ui.error = None;
+                                    }
+                                    Err(error) => {
+                                        record_ui.status = None;
+                                        record_ui.error = Some(error.to_string());
+                                    }
+                                }
+                                Command::none()
+                            }
+                            RecordUiMessage::DeleteFile(filename) => {
+                                let (Some(record_ui), Some(record)) = (
+                                    self.records.iter_mut().find(|r| r.id == id),
+                                    data.root.find_record_mut(id),
+                                ) else {
+                                    return Command::none();
+                                };
+                                match record.remove_file(&filename) {
+                                    Ok(_) => {
+                                        record_ui.refresh_files(record);
+                                        self.encrypt();
+                                    }
+                                    Err(error) => record_ui.error = Some(error.to_string()),
+                                }
+                                Command::none()
+                            }
+                            RecordUiMessage::Drag => {
+                                self.dragging = Some(TreeItem::Record(id));
+                                self.tree_error = None;
+                                Command::none()
+                            }
+                            RecordUiMessage::Duplicate => {
+                                let result = data.root.copy_record(id).map(|_| ());
+                                self.tree_changed(result)
+                            }
+                            RecordUiMessage::Delete => {
+                                let result = data.trash_record(id);
+                                self.tree_changed(result)
+                            }
+                            RecordUiMessage::ApplyTemplate(name) => {
+                                let (Some(record_ui), Some(template)) = (
+                                    self.records.iter_mut().find(|r| r.id == id),
+                                    data.find_template(&name),
+                                ) else {
+                                    return Command::none();
+                                };
+                                let record = Record::from_template(&template);
+                                record_ui.state = RecordUiState::Edit(field_drafts(&record.fields));
+                                record_ui.template = Some(name);
+                                record_ui.template_attachments = template.attachments;
+                                Command::none()
+                            }
+                            RecordUiMessage::SaveAsTemplate => {
+                                let (Some(record_ui), Some(record)) = (
+                                    self.records.iter_mut().find(|r| r.id == id),
+                                    data.root.find_record(id),
+                                ) else {
+                                    return Command::none();
+                                };
+                                let template = RecordTemplate::from_record(&record.display_name(), record);
+                                match data.add_template(template) {
+                                    Ok(()) => {
+                                        self.templates = template_names(data);
+                                        self.encrypt();
+                                    }
+                                    Err(error) => record_ui.error = Some(error.to_string()),
+                                }
+                                Command::none()
+                            }
+                            RecordUiMessage::Restore(version) => {
+                                let Some(record) = data.root.find_record_mut(id) else {
+                                    return Command::none();
+                                };
+                                if record.restore(version).is_err() {
+                                    return Command::none();
+                                }
+                                if let Some(record_ui) = self.records.iter_mut().find(|r| r.id == id) {
+                                    *record_ui = RecordUi::from_record(record);
+                                    record_ui.show_history = true;
+                                }
                                 self.encrypt();
                                 Command::none()
                             }
                             _ => {
-                                self.records[index].update(msg);
+                                if let Some(record_ui) = self.records.iter_mut().find(|r| r.id == id) {
+                                    record_ui.update(msg);
+                                }
                                 Command::none()
                             }
                         }
@@ -171,181 +541,1029 @@ impl Application for NordstoneUi {
                 ].into()
             }
             MainState::Decrypted(data) => {
-                match &data.subfolders {
-                    Some(subs) => {
-                        column(
-                            subs.iter().enumerate().map(|(index, s)| {
-                                return if Some(index) == self.subfolder_to_edit {
-                                    let fields = column(
-                                        self.records.iter().enumerate().map(|(index, r)| {
-                                            r.view().map(move |m| {
-                                                MainMessage::RecordUiMessage((index, m))
-                                            })
-                                        }).collect()
-                                    );
-                                    println!("{:?}", self.records);
-                                    column![
-                                        row![
-                                        text_input("input folder name", &s.name).on_input(move |name| {
-                                            MainMessage::ChangeFolder((index, name))
-                                        }),
-                                        button("save").on_press(MainMessage::Save)
-                                        ],
-                                        row![fields]
-                                    ].into()
-                                } else {
-                                    row![
-                                    text(s.name.clone()),
-                                    button("edit").on_press(
-                                        MainMessage::EditFolder((index))
-                                    )
-                                    ].into()
-                                };
-                            }).collect()
-                        ).into()
+                let record_filter = RecordFilter {
+                    tags: self.selected_tags.clone(),
+                    favorites_only: self.favorites_only,
+                };
+                let filter = if self.search.is_empty() && record_filter == RecordFilter::default() {
+                    None
+                } else {
+                    Some(SearchFilter::new(data.root.search_filtered(&self.search, &record_filter)))
+                };
+                let search = text_input("search", &self.search).on_input(MainMessage::Search);
+                let tree: Element<Self::Message> = if self.show_members {
+                    self.members_view(data)
+                } else if self.show_trash {
+                    self.trash_view(data)
+                } else {
+                    let mut rows = Vec::new();
+                    if let Some(filter) = &filter {
+                        rows.push(self.search_results_view(&data.root, filter));
                     }
-                    None => text("NO FOLDERS").into()
+                    rows.push(self.root_view(&data.root, filter.as_ref()));
+                    if let Some(subs) = &data.root.subfolders {
+                        rows.extend(self.folders_view(subs, filter.as_ref(), 0));
+                    }
+                    column(rows).into()
+                };
+                let main = row![
+                    self.sidebar_view(data),
+                    column![search, self.drag_view(&data.root), tree].width(Length::Fill)
+                ].spacing(20);
+                match &self.storage_error {
+                    Some(error) => {
+                        let banner = storage_error_banner(error, self.confirm_dropping_keys);
+                        column![banner, main].spacing(10).into()
+                    }
+                    None => main.into(),
                 }
             }
         }
     }
 }
 
+/// Folders and records that are shown while a search is active.
+struct SearchFilter {
+    results: Vec<RecordPath>,
+    folders: HashSet<Uuid>,
+    records: HashSet<Uuid>,
+}
+
+impl SearchFilter {
+    fn new(results: Vec<RecordPath>) -> Self {
+        let folders = results.iter().flat_map(|path| path.folders.iter().copied()).collect();
+        let records = results.iter().map(|path| path.record).collect();
+        Self { results, folders, records }
+    }
+}
+
+impl NordstoneUi {
+    /// Persists a tree operation that succeeded, or shows why it failed.
+    fn tree_changed(&mut self, result: Result<(), ModelsError>) -> Command<MainMessage> {
+        match result {
+            Ok(()) => {
+                self.tree_error = None;
+                self.refresh_records();
+                self.encrypt();
+            }
+            Err(error) => self.tree_error = Some(error.to_string()),
+        }
+        Command::none()
+    }
+
+    /// While something is dragged, a hint and a drop target for the top level of the tree.
+    fn drag_view(&self, root: &Folder) -> Element<'_, MainMessage> {
+        let error = text(self.tree_error.clone().unwrap_or_default());
+        if self.dragging.is_none() {
+            return error.into();
+        }
+        column![
+            row![
+                text("release over a folder to move it there"),
+                button("cancel").on_press(MainMessage::CancelDrag)
+            ].spacing(10),
+            mouse_area(text("[top level]")).on_release(MainMessage::Drop(root.id()))
+        ].into()
+    }
+
+    /// Who the vault is encrypted to, with a form to add a teammate's public key.
+    /// Saving after a change re-encrypts the vault to the new set of keys.
+    fn members_view(&self, data: &Vault) -> Element<'_, MainMessage> {
+        let Some(encryptor) = self.encryptor.as_ref().filter(|e| !e.is_passphrase()) else {
+            return text("This vault is protected by a passphrase, open it with an identity file or SSH key to share it with members.").into();
+        };
+        let own_keys: Vec<String> = encryptor.recipients().iter().map(|key| key.to_string()).collect();
+        let unsaved_keys = column(
+            own_keys.iter().filter(|key| data.members().iter().all(|m| &m.public_key != *key)).map(|key| {
+                row![text("you (stored as a member on save)"), text(key).size(12)].spacing(10).into()
+            }).collect()
+        ).spacing(5);
+        let members = column(
+            data.members().iter().map(|member| {
+                let label = if own_keys.contains(&member.public_key) {
+                    format!("{} (you)", member.label)
+                } else {
+                    member.label.clone()
+                };
+                row![
+                    text(label),
+                    text(&member.public_key).size(12),
+                    button("remove").on_press(MainMessage::RemoveMember(member.public_key.clone()))
+                ].spacing(10).into()
+            }).collect()
+        ).spacing(5);
+        column![
+            text("encrypted to"),
+            unsaved_keys,
+            members,
+            row![
+                text_input("name", &self.member_label)
+                    .on_input(MainMessage::EditMemberLabel)
+                    .width(150),
+                text_input("public key (age1... or ssh-ed25519 ...)", &self.member_key)
+                    .on_input(MainMessage::EditMemberKey)
+                    .on_submit(MainMessage::AddMember),
+                button("add member").on_press(MainMessage::AddMember)
+            ].spacing(10)
+        ].spacing(10).into()
+    }
+
+    /// Deleted items with where they were deleted from, and the retention setting.
+    fn trash_view(&self, data: &Vault) -> Element<'_, MainMessage> {
+        let trash = data.trash();
+        let entries = column(
+            trash.entries().iter().enumerate().map(|(index, entry)| {
+                row![
+                    text(trash_label(entry)),
+                    text(format!("/{}", entry.path().join("/"))),
+                    text(format!("deleted {}", format_timestamp(entry.deleted()))).size(12),
+                    button("restore").on_press(MainMessage::RestoreFromTrash(index)),
+                    button("delete forever").on_press(MainMessage::DeleteFromTrash(index))
+                ].spacing(10).into()
+            }).collect()
+        ).spacing(5);
+        column![
+            row![
+                text("keep deleted items for"),
+                text_input("days", &self.trash_retention)
+                    .on_input(MainMessage::EditTrashRetention)
+                    .on_submit(MainMessage::SetTrashRetention)
+                    .width(80),
+                text("days"),
+                button("set").on_press(MainMessage::SetTrashRetention),
+                button("empty trash").on_press(MainMessage::EmptyTrash)
+            ].spacing(10),
+            entries
+        ].spacing(10).into()
+    }
+
+    /// Favorites toggle and every tag used in the vault, selected ones highlighted.
+    fn sidebar_view(&self, data: &Vault) -> Element<'_, MainMessage> {
+        let style = |selected: bool| {
+            if selected { theme::Button::Primary } else { theme::Button::Secondary }
+        };
+        let mut sidebar = column![
+            button("favorites")
+                .style(style(self.favorites_only))
+                .on_press(MainMessage::ToggleFavorites),
+            button(text(format!("trash ({})", data.trash().entries().len())))
+                .style(style(self.show_trash))
+                .on_press(MainMessage::ToggleTrash),
+            button(text(format!("members ({})", data.members().len())))
+                .style(style(self.show_members))
+                .on_press(MainMessage::ToggleMembers)
+        ].spacing(5);
+        for tag in data.root.tags() {
+            sidebar = sidebar.push(
+                button(text(tag.clone()))
+                    .style(style(self.selected_tags.contains(&tag)))
+                    .on_press(MainMessage::ToggleTag(tag))
+            );
+        }
+        sidebar.into()
+    }
+
+    /// Every record matching the search, with the folders leading to it and a button
+    /// to edit the folder it is in.
+    fn search_results_view(&self, root: &Folder, filter: &SearchFilter) -> Element<'_, MainMessage> {
+        let mut results = column![text(format!("{} matching records", filter.results.len()))].spacing(5);
+        for result in &filter.results {
+            let Some(record) = root.find_record(result.record) else {
+                continue;
+            };
+            let mut path = vec![root.name.as_str()];
+            path.extend(result.folders.iter().filter_map(|&id| root.find_folder(id)).map(|f| f.name.as_str()));
+            let folder_id = result.folders.last().copied().unwrap_or(root.id());
+            results = results.push(row![
+                text(path.join(" / ")),
+                text(record.display_name()),
+                button("open").on_press(MainMessage::EditFolder(folder_id))
+            ].spacing(10));
+        }
+        results.into()
+    }
+
+    /// Row of the root folder, followed by the editors of its records while it is edited.
+    fn root_view<'a>(&'a self, root: &'a Folder, filter: Option<&SearchFilter>) -> Element<'a, MainMessage> {
+        let root_id = root.id();
+        if Some(root_id) != self.subfolder_to_edit {
+            return row![
+                text(root.name.clone()),
+                button("edit").on_press(MainMessage::EditFolder(root_id))
+            ].spacing(10).into();
+        }
+        column![
+            row![
+                text(root.name.clone()),
+                button("save").on_press(MainMessage::Save),
+                button("sort by title").on_press(MainMessage::SortRecords(root_id))
+            ].spacing(10),
+            self.records_view(filter)
+        ].into()
+    }
+
+    /// Editors of the records of the edited folder, only the matching ones while a
+    /// search is active.
+    fn records_view(&self, filter: Option<&SearchFilter>) -> Element<'_, MainMessage> {
+        column(
+            self.records.iter().filter(|r| match filter {
+                Some(f) => f.records.contains(&r.id),
+                None => true,
+            }).map(|r| {
+                let record_id = r.id;
+                r.view(&self.templates).map(move |m| {
+                    MainMessage::RecordUiMessage((record_id, m))
+                })
+            }).collect()
+        ).into()
+    }
+
+    /// Rows of the folder tree, subfolders indented below their parent.
+    fn folders_view<'a>(
+        &'a self,
+        subfolders: &'a [Folder],
+        filter: Option<&SearchFilter>,
+        depth: u16,
+    ) -> Vec<Element<'a, MainMessage>> {
+        let mut rows = Vec::new();
+        for s in subfolders {
+            let folder_id = s.id();
+            if filter.is_some_and(|f| !f.folders.contains(&folder_id)) {
+                continue;
+            }
+            if Some(folder_id) == self.subfolder_to_edit {
+                let fields = self.records_view(filter);
+                let header = row![
+                    drag_handle(MainMessage::Drag(TreeItem::Folder(folder_id))),
+                    text_input("input folder name", &s.name).on_input(move |name| {
+                        MainMessage::ChangeFolder((folder_id, name))
+                    }),
+                    button("save").on_press(MainMessage::Save),
+                    button("sort by title").on_press(MainMessage::SortRecords(folder_id)),
+                    button("delete").on_press(MainMessage::DeleteFolder(folder_id))
+                ];
+                rows.push(column![
+                    mouse_area(header).on_release(MainMessage::Drop(folder_id)),
+                    row![fields]
+                ].padding([0, 0, 0, depth * 20]).into());
+            } else {
+                let header = row![
+                    drag_handle(MainMessage::Drag(TreeItem::Folder(folder_id))),
+                    text(s.name.clone()),
+                    button("edit").on_press(
+                        MainMessage::EditFolder(folder_id)
+                    ),
+                    button("delete").on_press(MainMessage::DeleteFolder(folder_id))
+                ].spacing(10).padding([0, 0, 0, depth * 20]);
+                rows.push(mouse_area(header).on_release(MainMessage::Drop(folder_id)).into());
+            }
+            if let Some(children) = &s.subfolders {
+                rows.extend(self.folders_view(children, filter, depth + 1));
+            }
+        }
+        rows
+    }
+}
+
 #[derive(Debug, Clone)]
 enum DecryptFormMessage {
     KeyChanged(String),
-    Decrypt(String),
+    IdentityFileChanged(String),
+    KeyPassphraseChanged(String),
+    Decrypt,
 }
 
+/// Unlocks the vault with a passphrase, or with an age identity file or SSH private key
+/// when one is given. A vault opened with a key is saved for its own public keys plus
+/// the vault members.
 #[derive(Debug)]
 struct DecryptForm {
     key: String,
+    identity_file: String,
+    key_passphrase: String,
+    /// Whether the SSH key turned out to be protected, showing the passphrase prompt.
+    ask_key_passphrase: bool,
+    ssh_keys: Vec<String>,
+    error: Option<String>,
 }
 
 impl DecryptForm {
     fn new() -> Self {
         Self {
-            key: "".into()
+            key: "".into(),
+            identity_file: "".into(),
+            key_passphrase: "".into(),
+            ask_key_passphrase: false,
+            ssh_keys: ssh_keys(),
+            error: None,
         }
     }
 
     fn update(&mut self, message: DecryptFormMessage) {
         match message {
-            DecryptFormMessage::KeyChanged(key) => {
-                self.key = key
+            DecryptFormMessage::KeyChanged(key) => self.key = key,
+            DecryptFormMessage::IdentityFileChanged(path) => {
+                self.identity_file = path;
+                self.key_passphrase.clear();
+                self.ask_key_passphrase = false;
             }
-            _ => {}
+            DecryptFormMessage::KeyPassphraseChanged(passphrase) => self.key_passphrase = passphrase,
+            DecryptFormMessage::Decrypt => return,
         }
+        self.error = None;
     }
 
-    fn view(&self) -> Element<DecryptFormMessage> {
-        row![
-            text_input("input key", &self.key).on_input(|key| {
-                DecryptFormMessage::KeyChanged(key)
-            }),
-            button("decrypt").on_press(DecryptFormMessage::Decrypt(self.key.clone()))
-        ].into()
+    fn encryptor(&self) -> Result<AgeEncryptor, EncryptionError> {
+        let identity_file = self.identity_file.trim();
+        if identity_file.is_empty() {
+            return Ok(AgeEncryptor::new(self.key.clone()));
+        }
+        let passphrase = Some(self.key_passphrase.clone()).filter(|p| !p.is_empty());
+        AgeEncryptor::from_key_file(&expand_home(identity_file), passphrase)
     }
+
+    fn show_error(&mut self, error: &StorageError) {
+        if matches!(error, StorageError::Encryption(EncryptionError::KeyPassphraseRequired)) {
+            self.ask_key_passphrase = true;
+        }
+        self.error = Some(decrypt_error_message(error));
+    }
+
+    fn view(&self) -> Element<'_, DecryptFormMessage> {
+        let passphrase = row![
+            text_input("input key", &self.key)
+                .on_input(DecryptFormMessage::KeyChanged)
+                .on_submit(DecryptFormMessage::Decrypt),
+            button("decrypt").on_press(DecryptFormMessage::Decrypt)
+        ];
+        let identity = text_input("or identity file or SSH key, e.g. ~/.ssh/id_ed25519", &self.identity_file)
+            .on_input(DecryptFormMessage::IdentityFileChanged)
+            .on_submit(DecryptFormMessage::Decrypt);
+        let ssh_keys = row(
+            self.ssh_keys.iter().map(|path| {
+                let name = Path::new(path).file_name().unwrap_or_default().to_string_lossy().to_string();
+                button(text(name))
+                    .style(theme::Button::Secondary)
+                    .on_press(DecryptFormMessage::IdentityFileChanged(path.clone()))
+                    .into()
+            }).collect()
+        ).spacing(5);
+        let mut form = column![passphrase, identity, ssh_keys].spacing(10);
+        if self.ask_key_passphrase {
+            form = form.push(
+                text_input("SSH key passphrase", &self.key_passphrase)
+                    .password()
+                    .on_input(DecryptFormMessage::KeyPassphraseChanged)
+                    .on_submit(DecryptFormMessage::Decrypt)
+            );
+        }
+        match &self.error {
+            Some(error) => form.push(text(error)).into(),
+            None => form.into(),
+        }
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory, as a shell would.
+fn expand_home(path: &str) -> PathBuf {
+    let home = home_dir().filter(|_| path == "~" || path.starts_with("~/"));
+    match home {
+        Some(home) => home.join(path.trim_start_matches('~').trim_start_matches('/')),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Private keys in `~/.ssh` named like the ones `ssh-keygen` creates, `id_ed25519` or `id_rsa`.
+fn ssh_keys() -> Vec<String> {
+    let Some(entries) = home_dir().and_then(|home| std::fs::read_dir(home.join(".ssh")).ok()) else {
+        return Vec::new();
+    };
+    let mut keys: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with("id_") && path.extension().is_none()
+        })
+        .map(|path| path.display().to_string())
+        .collect();
+    keys.sort();
+    keys
+}
+
+fn decrypt_error_message(error: &StorageError) -> String {
+    match error {
+        StorageError::Encryption(EncryptionError::WrongKey) => "Wrong passphrase or key, please try again".into(),
+        StorageError::Encryption(EncryptionError::KeyPassphraseRequired) => "Enter the passphrase of the SSH key".into(),
+        error => format!("Could not open the vault: {}", error),
+    }
+}
+
+/// Storage of the vault file in the home directory.
+fn vault_storage(encryptor: AgeEncryptor) -> Result<LocalStorageManager, StorageError> {
+    let config_path = home_dir().ok_or(StorageError::HomeDirNotFound)?.join("nordstone.cfg");
+    Ok(LocalStorageManager::new(config_path, encryptor))
+}
+
+/// Vault stored in the home directory, or a new one if there is none yet.
+fn open_vault(encryptor: &AgeEncryptor) -> Result<Vault, StorageError> {
+    let config_path = home_dir().ok_or(StorageError::HomeDirNotFound)?.join("nordstone.cfg");
+    if !config_path.exists() {
+        return Ok(Vault::new(Folder::new("NEW FOLDER".into())));
+    }
+    vault_storage(encryptor.clone())?.load()
 }
 
 #[derive(Debug, Clone)]
 enum RecordUiMessage {
-    Save(HashMap<String, String>),
-    Change(HashMap<String, String>),
+    Save(FieldDrafts),
+    Change(FieldDrafts),
+    /// Edit of the fields that was refused, with the reason.
+    Reject(String),
     Edit((String, String)),
+    EditKind(FieldKind),
+    EditTitle(String),
+    EditIcon(String),
+    EditUrl(String),
+    EditTags(String),
+    ToggleFavorite(bool),
+    ToggleReveal(String),
+    OpenUrl(String),
+    ToggleHistory,
+    Restore(usize),
+    EditFileToAttach(String),
+    EditExportDirectory(String),
+    Browse(PathBuf),
+    CloseBrowser,
+    AttachFile,
+    ExportFile(OsString),
+    DeleteFile(OsString),
+    Drag,
+    Duplicate,
+    Delete,
+    ApplyTemplate(String),
+    SaveAsTemplate,
+}
+
+/// What the record editor shows about an attachment.
+#[derive(Debug, Clone)]
+struct FileInfo {
+    filename: OsString,
+    extension: String,
+    size: usize,
+}
+
+/// Directory listing to pick a file to attach or the directory to export to.
+#[derive(Debug)]
+struct Browser {
+    directory: PathBuf,
+    subdirectories: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+impl Browser {
+    /// Lists `directory`, hidden entries left out, or the home directory if `directory`
+    /// does not exist. An unreadable directory is shown empty.
+    fn open(directory: PathBuf) -> Self {
+        let directory = if directory.is_dir() { directory } else { home_dir().unwrap_or_default() };
+        let mut browser = Self { directory, subdirectories: Vec::new(), files: Vec::new() };
+        let Ok(entries) = std::fs::read_dir(&browser.directory) else {
+            return browser;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.file_name().unwrap_or_default().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                browser.subdirectories.push(path);
+            } else {
+                browser.files.push(path);
+            }
+        }
+        browser.subdirectories.sort();
+        browser.files.sort();
+        browser
+    }
+
+    /// Subdirectories open in the browser, files are picked for attaching, and the
+    /// directory itself can be picked for exporting.
+    fn view(&self) -> Element<'_, RecordUiMessage> {
+        let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let mut up = button("..");
+        if let Some(parent) = self.directory.parent() {
+            up = up.on_press(RecordUiMessage::Browse(parent.to_path_buf()));
+        }
+        let header = row![
+            text(self.directory.display().to_string()),
+            up,
+            button("export here").on_press(RecordUiMessage::EditExportDirectory(
+                self.directory.display().to_string()
+            )),
+            button("close").on_press(RecordUiMessage::CloseBrowser)
+        ].spacing(10);
+        let subdirectories = self.subdirectories.iter().map(|path| {
+            button(text(format!("{}/", name(path))))
+                .style(theme::Button::Secondary)
+                .on_press(RecordUiMessage::Browse(path.clone()))
+                .into()
+        });
+        let files = self.files.iter().map(|path| {
+            button(text(name(path)))
+                .style(theme::Button::Text)
+                .on_press(RecordUiMessage::EditFileToAttach(path.display().to_string()))
+                .into()
+        });
+        column![header, column(subdirectories.chain(files).collect())].spacing(5).into()
+    }
+}
+
+/// Field as it is being edited: its kind and the text typed so far.
+#[derive(Debug, Clone)]
+struct FieldDraft {
+    kind: FieldKind,
+    value: String,
+}
+
+type FieldDrafts = IndexMap<String, FieldDraft>;
+
+fn field_drafts(fields: &IndexMap<String, FieldValue>) -> FieldDrafts {
+    fields.iter().map(|(k, v)| {
+        (k.clone(), FieldDraft { kind: v.kind(), value: v.to_string() })
+    }).collect()
+}
+
+fn field_values(drafts: FieldDrafts) -> Result<IndexMap<String, FieldValue>, ModelsError> {
+    drafts.into_iter().map(|(k, draft)| {
+        Ok((k, FieldValue::new(draft.kind, &draft.value)?))
+    }).collect()
 }
 
 #[derive(Debug, Clone)]
 enum RecordUiState {
-    Display(HashMap<String, String>),
-    Edit(HashMap<String, String>),
+    Edit(FieldDrafts),
 }
 
 #[derive(Debug)]
 struct RecordUi {
+    id: Uuid,
     state: RecordUiState,
     key_to_add: String,
     value_to_add: String,
+    kind_to_add: FieldKind,
+    display_name: String,
+    title: String,
+    icon: String,
+    url: String,
+    tags_text: String,
+    favorite: bool,
+    revealed: HashSet<String>,
+    error: Option<String>,
+    /// Outcome of the last export.
+    status: Option<String>,
+    created: Option<DateTime<Utc>>,
+    modified: Option<DateTime<Utc>>,
+    history: Vec<RecordVersion>,
+    show_history: bool,
+    files: Vec<FileInfo>,
+    file_to_attach: String,
+    export_directory: String,
+    browser: Option<Browser>,
+    template: Option<String>,
+    template_attachments: Vec<String>,
 }
 
 impl RecordUi {
-    fn new(fields: HashMap<String, String>) -> Self {
+    fn new(id: Uuid, fields: FieldDrafts) -> Self {
         Self {
-            state: RecordUiState::Display(fields),
+            id,
+            state: RecordUiState::Edit(fields),
             key_to_add: "".to_string(),
             value_to_add: "".to_string(),
+            kind_to_add: FieldKind::Text,
+            display_name: "new record".to_string(),
+            title: "".to_string(),
+            icon: "".to_string(),
+            url: "".to_string(),
+            tags_text: "".to_string(),
+            favorite: false,
+            revealed: HashSet::new(),
+            error: None,
+            status: None,
+            created: None,
+            modified: None,
+            history: Vec::new(),
+            show_history: false,
+            files: Vec::new(),
+            file_to_attach: "".to_string(),
+            export_directory: home_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            browser: None,
+            template: None,
+            template_attachments: Vec::new(),
         }
     }
 
+    fn from_record(record: &Record) -> Self {
+        Self {
+            created: Some(record.created()),
+            modified: Some(record.modified()),
+            history: record.history().to_vec(),
+            display_name: record.display_name(),
+            title: record.title().to_string(),
+            icon: record.icon().unwrap_or_default().to_string(),
+            url: record.url().unwrap_or_default().to_string(),
+            tags_text: record.tags().iter().cloned().collect::<Vec<_>>().join(", "),
+            favorite: record.is_favorite(),
+            files: file_infos(record),
+            ..Self::new(record.id(), field_drafts(&record.fields))
+        }
+    }
+
+    /// Copies everything edited here except the fields onto `record`.
+    fn apply_details(&self, record: &mut Record) {
+        record.set_title(&self.title);
+        record.set_icon(Some(self.icon.trim().to_string()));
+        record.set_url(Some(self.url.trim().to_string()));
+        record.set_tags(self.tags());
+        record.set_favorite(self.favorite);
+    }
+
+    fn refresh_files(&mut self, record: &Record) {
+        self.files = file_infos(record);
+        self.modified = Some(record.modified());
+        self.error = None;
+        self.status = None;
+    }
+
+    fn tags(&self) -> BTreeSet<String> {
+        self.tags_text.split(',').map(|t| t.trim().to_string()).collect()
+    }
+
     fn update(&mut self, message: RecordUiMessage) {
-        match self.state {
-            RecordUiState::Edit(_) => {
-                match message {
-                    RecordUiMessage::Save(_) => {}
-                    RecordUiMessage::Change(new_data) => {
-                        self.state = RecordUiState::Edit(new_data);
-                    }
-                    RecordUiMessage::Edit((k, v)) => {
-                        self.key_to_add = k;
-                        self.value_to_add = v;
-                    }
+        match message {
+            RecordUiMessage::Save(_)
+            | RecordUiMessage::Restore(_)
+            | RecordUiMessage::AttachFile
+            | RecordUiMessage::ExportFile(_)
+            | RecordUiMessage::DeleteFile(_)
+            | RecordUiMessage::Drag
+            | RecordUiMessage::Duplicate
+            | RecordUiMessage::Delete
+            | RecordUiMessage::ApplyTemplate(_)
+            | RecordUiMessage::SaveAsTemplate => {}
+            RecordUiMessage::EditFileToAttach(path) => {
+                self.file_to_attach = path;
+            }
+            RecordUiMessage::EditExportDirectory(directory) => {
+                self.export_directory = directory;
+            }
+            RecordUiMessage::Browse(directory) => {
+                self.browser = Some(Browser::open(directory));
+            }
+            RecordUiMessage::CloseBrowser => {
+                self.browser = None;
+            }
+            RecordUiMessage::Change(new_data) => {
+                self.state = RecordUiState::Edit(new_data);
+                self.error = None;
+            }
+            RecordUiMessage::Reject(error) => {
+                self.error = Some(error);
+            }
+            RecordUiMessage::Edit((k, v)) => {
+                self.key_to_add = k;
+                self.value_to_add = v;
+            }
+            RecordUiMessage::EditKind(kind) => {
+                self.kind_to_add = kind;
+            }
+            RecordUiMessage::EditTitle(title) => {
+                self.title = title;
+            }
+            RecordUiMessage::EditIcon(icon) => {
+                self.icon = icon;
+            }
+            RecordUiMessage::EditUrl(url) => {
+                self.url = url;
+            }
+            RecordUiMessage::EditTags(tags) => {
+                self.tags_text = tags;
+            }
+            RecordUiMessage::ToggleFavorite(favorite) => {
+                self.favorite = favorite;
+            }
+            RecordUiMessage::ToggleReveal(k) => {
+                if !self.revealed.remove(&k) {
+                    self.revealed.insert(k);
                 }
             }
-            _ => {}
+            RecordUiMessage::OpenUrl(url) => {
+                if let Err(error) = open_url(&url) {
+                    self.error = Some(format!("Could not open {}: {}", url, error));
+                }
+            }
+            RecordUiMessage::ToggleHistory => {
+                self.show_history = !self.show_history;
+            }
         }
     }
 
-    fn view(&self) -> Element<RecordUiMessage> {
+    /// Editor of the record; `templates` are offered while the record is not saved yet.
+    fn view<'a>(&'a self, templates: &'a [String]) -> Element<'a, RecordUiMessage> {
         match &self.state {
-            RecordUiState::Display(data) => {
-                column(
-                    data
-                        .iter()
-                        .map(|(k, v)| {
-                            text(format!("{}:{}", k, v)).into()
-                        })
-                        .collect()
-                ).into()
-            }
             RecordUiState::Edit(data) => {
                 let existing: Column<RecordUiMessage> = column(
                     data
                         .iter()
-                        .map(|(k, v)| {
-                            row![
-                                text_input("input name", k).on_input(|new_key| {
-                                    let mut new_data = data.clone();
-                                    new_data.remove(k);
-                                    new_data.insert(k.into(), v.into());
-                                    RecordUiMessage::Change(new_data)
-                                }),
-                                text_input("input value", k).on_input(|new_value| {
-                                    let mut new_data = data.clone();
-                                    new_data.insert(k.into(), new_value);
-                                    RecordUiMessage::Change(new_data)
-                                })
-                            ].into()
-                        }).collect()
-                ).into();
+                        .enumerate()
+                        .map(|(index, (k, v))| self.field_view(data, index, k, v))
+                        .collect()
+                );
                 let mut new_data = data.clone();
-                new_data.insert(self.key_to_add.clone(), self.value_to_add.clone());
+                let save = if self.key_to_add.is_empty() {
+                    RecordUiMessage::Save(new_data)
+                } else if data.contains_key(&self.key_to_add) {
+                    RecordUiMessage::Reject(ModelsError::FieldAlreadyExist.to_string())
+                } else {
+                    new_data.insert(self.key_to_add.clone(), FieldDraft {
+                        kind: self.kind_to_add,
+                        value: self.value_to_add.clone(),
+                    });
+                    RecordUiMessage::Save(new_data)
+                };
+                let timestamps = match (self.created, self.modified) {
+                    (Some(created), Some(modified)) => format!(
+                        "created {} / modified {}",
+                        format_timestamp(created),
+                        format_timestamp(modified),
+                    ),
+                    _ => "new record".to_string(),
+                };
+                let error = text(self.error.clone().unwrap_or_default());
+                let headline = text(format!("{} {}", self.icon, self.display_name).trim().to_string()).size(20);
+                let header: Element<RecordUiMessage> = if self.created.is_some() {
+                    row![
+                        drag_handle(RecordUiMessage::Drag),
+                        headline,
+                        text(timestamps).size(12),
+                        button("duplicate").on_press(RecordUiMessage::Duplicate),
+                        button("save as template").on_press(RecordUiMessage::SaveAsTemplate),
+                        button("delete").on_press(RecordUiMessage::Delete)
+                    ].spacing(10).into()
+                } else {
+                    let attachments = if self.template_attachments.is_empty() {
+                        String::new()
+                    } else {
+                        format!("attach after saving: {}", self.template_attachments.join(", "))
+                    };
+                    row![
+                        headline,
+                        text(timestamps).size(12),
+                        pick_list(templates, self.template.clone(), RecordUiMessage::ApplyTemplate)
+                            .placeholder("template"),
+                        text(attachments).size(12)
+                    ].spacing(10).into()
+                };
+                let mut open = button("open");
+                if !self.url.trim().is_empty() {
+                    open = open.on_press(RecordUiMessage::OpenUrl(self.url.trim().to_string()));
+                }
                 column![
+                    header,
+                    row![
+                        text_input("icon", &self.icon).on_input(RecordUiMessage::EditIcon).width(60),
+                        text_input("title", &self.title).on_input(RecordUiMessage::EditTitle),
+                        text_input("website", &self.url).on_input(RecordUiMessage::EditUrl),
+                        open
+                    ].spacing(10),
                     existing,
                     row![
                         text_input("input name", &self.key_to_add).on_input(|k| {
                             RecordUiMessage::Edit((k, self.value_to_add.clone()))
                         }),
+                        pick_list(&FieldKind::ALL[..], Some(self.kind_to_add), RecordUiMessage::EditKind),
                         text_input("input value", &self.value_to_add).on_input(|v| {
                             RecordUiMessage::Edit((self.key_to_add.clone(), v))
                         })
                     ],
-                    button("save fields").on_press(RecordUiMessage::Save(
-                        new_data
-                    ))
+                    row![
+                        checkbox("favorite", self.favorite, RecordUiMessage::ToggleFavorite),
+                        text_input("tags, comma separated", &self.tags_text).on_input(RecordUiMessage::EditTags)
+                    ].spacing(10),
+                    error,
+                    row![
+                        button("save fields").on_press(save),
+                        button("history").on_press(RecordUiMessage::ToggleHistory)
+                    ],
+                    self.files_view(),
+                    self.history_view()
                 ].into()
             }
         }
     }
+
+    /// Editor of a single field: secrets are masked until revealed, urls can be opened
+    /// and the field can be moved up or down.
+    fn field_view<'a>(&'a self, data: &'a FieldDrafts, index: usize, k: &'a String, v: &'a FieldDraft) -> Element<'a, RecordUiMessage> {
+        let name = text_input("input name", k).on_input(move |new_key| {
+            if new_key.is_empty() {
+                return RecordUiMessage::Reject(ModelsError::EmptyFieldName.to_string());
+            }
+            if new_key != *k && data.contains_key(&new_key) {
+                return RecordUiMessage::Reject(ModelsError::FieldAlreadyExist.to_string());
+            }
+            let new_data = data.iter().map(|(key, draft)| {
+                let key = if key == k { new_key.clone() } else { key.clone() };
+                (key, draft.clone())
+            }).collect();
+            RecordUiMessage::Change(new_data)
+        });
+        let moved = |to: usize| {
+            let mut new_data = data.clone();
+            new_data.move_index(index, to);
+            RecordUiMessage::Change(new_data)
+        };
+        let mut up = button("up");
+        if index > 0 {
+            up = up.on_press(moved(index - 1));
+        }
+        let mut down = button("down");
+        if index + 1 < data.len() {
+            down = down.on_press(moved(index + 1));
+        }
+        let kind = pick_list(&FieldKind::ALL[..], Some(v.kind), |kind| {
+            let mut new_data = data.clone();
+            new_data.insert(k.into(), FieldDraft { kind, value: v.value.clone() });
+            RecordUiMessage::Change(new_data)
+        });
+        let secret = matches!(v.kind, FieldKind::Concealed | FieldKind::Totp);
+        let mut value = text_input("input value", &v.value).on_input(|new_value| {
+            let mut new_data = data.clone();
+            new_data.insert(k.into(), FieldDraft { kind: v.kind, value: new_value });
+            RecordUiMessage::Change(new_data)
+        });
+        if secret && !self.revealed.contains(k) {
+            value = value.password();
+        }
+        let action: Element<RecordUiMessage> = match v.kind {
+            _ if secret => {
+                let label = if self.revealed.contains(k) { "hide" } else { "show" };
+                button(label).on_press(RecordUiMessage::ToggleReveal(k.clone())).into()
+            }
+            FieldKind::Url => button("open").on_press(RecordUiMessage::OpenUrl(v.value.clone())).into(),
+            _ => row![].into(),
+        };
+        row![up, down, name, kind, value, action].into()
+    }
+
+    /// Attachments with size and extension, plus inputs to attach and export files, and
+    /// a browser to pick them instead of typing the paths. Files can only be attached
+    /// to records that were saved already.
+    fn files_view(&self) -> Element<'_, RecordUiMessage> {
+        if self.created.is_none() {
+            return column![].into();
+        }
+        let files = column(
+            self.files.iter().map(|f| {
+                row![
+                    text(f.filename.to_string_lossy()),
+                    text(&f.extension),
+                    text(format_size(f.size)),
+                    button("export").on_press(RecordUiMessage::ExportFile(f.filename.clone())),
+                    button("delete").on_press(RecordUiMessage::DeleteFile(f.filename.clone()))
+                ].spacing(10).into()
+            }).collect()
+        );
+        column![
+            files,
+            row![
+                text_input("path of file to attach", &self.file_to_attach)
+                    .on_input(RecordUiMessage::EditFileToAttach)
+                    .on_submit(RecordUiMessage::AttachFile),
+                button("attach").on_press(RecordUiMessage::AttachFile)
+            ],
+            row![
+                text("export to"),
+                text_input("directory", &self.export_directory)
+                    .on_input(RecordUiMessage::EditExportDirectory),
+                button("browse").on_press(RecordUiMessage::Browse(self.export_directory.trim().into()))
+            ].spacing(10),
+            text(self.status.clone().unwrap_or_default()),
+            match &self.browser {
+                Some(browser) => browser.view(),
+                None => column![].into(),
+            }
+        ].into()
+    }
+
+    /// Previous versions of the record, newest first, each with a restore button.
+    fn history_view(&self) -> Element<'_, RecordUiMessage> {
+        if !self.show_history {
+            return column![].into();
+        }
+        if self.history.is_empty() {
+            return text("no previous versions").into();
+        }
+        column(
+            self.history.iter().enumerate().rev().map(|(index, version)| {
+                let mut fields: Vec<String> = version.fields
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v.masked()))
+                    .collect();
+                fields.sort();
+                row![
+                    text(format_timestamp(version.modified)),
+                    text(fields.join(", ")),
+                    button("restore").on_press(RecordUiMessage::Restore(index))
+                ].spacing(10).into()
+            }).collect()
+        ).into()
+    }
+}
+
+/// Opens `url` with the default handler of the desktop environment. Only http and
+/// https links are opened, since the handler would run anything else it is given,
+/// such as a local program.
+fn open_url(url: &str) -> std::io::Result<()> {
+    let url = url::Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(|| std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "only http and https links can be opened",
+        ))?;
+    let child = if cfg!(target_os = "windows") {
+        std::process::Command::new("rundll32").args(["url.dll,FileProtocolHandler", url.as_str()]).spawn()
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg(url.as_str()).spawn()
+    } else {
+        std::process::Command::new("xdg-open").arg(url.as_str()).spawn()
+    };
+    child.map(drop)
+}
+
+/// Why the vault was not saved, with a button to save anyway when the save was only
+/// held back because it would drop keys.
+fn storage_error_banner(error: &str, confirm_dropping_keys: bool) -> Element<'_, MainMessage> {
+    let mut banner = row![
+        text(format!("Vault not saved: {}", error)).style(Color::from_rgb(0.8, 0.1, 0.1)),
+        button("dismiss").on_press(MainMessage::DismissStorageError)
+    ].spacing(10);
+    if confirm_dropping_keys {
+        banner = banner.push(button("save anyway").on_press(MainMessage::SaveDroppingKeys));
+    }
+    banner.into()
+}
+
+fn trash_label(entry: &TrashEntry) -> String {
+    match &entry.item {
+        TrashedItem::Folder(folder) => format!("folder {}", folder.name),
+        TrashedItem::Record(record) => format!("record {}", record.display_name()),
+    }
+}
+
+/// Handle that starts dragging an item of the tree when pressed.
+fn drag_handle<'a, Message: Clone + 'a>(message: Message) -> Element<'a, Message> {
+    mouse_area(text("[move]")).on_press(message).into()
+}
+
+fn template_names(data: &Vault) -> Vec<String> {
+    data.all_templates().into_iter().map(|t| t.name).collect()
+}
+
+fn file_infos(record: &Record) -> Vec<FileInfo> {
+    record
+        .files()
+        .iter()
+        .map(|f| FileInfo {
+            filename: f.filename().to_os_string(),
+            extension: f
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            size: f.size(),
+        })
+        .collect()
+}
+
+fn format_size(size: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
 }
 
 #[tokio::main]
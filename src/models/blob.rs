@@ -0,0 +1,214 @@
+//! Serialized vault layout: attachment content is stored once in a blob table
+//! keyed by checksum, and records only keep the checksum as a reference.
+//! The trash, the user-defined templates and the members of the vault, along with the
+//! templates and members that were removed, are stored after the folder tree, and the
+//! whole image is preceded by a magic number and a format version (see
+//! [`migration`](super::migration) for how older layouts are read).
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde::de::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::models::migration::{FORMAT_VERSION, MAGIC};
+use crate::models::{Folder, Record, RecordTemplate, Removed, Trash, TrashedItem, Vault, VaultMember};
+
+#[derive(Serialize)]
+struct VaultImageRef<'a> {
+    blobs: BTreeMap<[u8; 32], &'a [u8]>,
+    root: &'a Folder,
+    trash: &'a Trash,
+    templates: &'a [RecordTemplate],
+    members: &'a [VaultMember],
+    removed_templates: &'a [Removed],
+    removed_members: &'a [Removed],
+}
+
+/// Image of the current format version.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+pub(crate) struct VaultImage {
+    pub(crate) blobs: BTreeMap<[u8; 32], Vec<u8>>,
+    pub(crate) root: Folder,
+    pub(crate) trash: Trash,
+    pub(crate) templates: Vec<RecordTemplate>,
+    pub(crate) members: Vec<VaultMember>,
+    pub(crate) removed_templates: Vec<Removed>,
+    pub(crate) removed_members: Vec<Removed>,
+}
+
+impl Vault {
+    /// Serializes the vault, storing each distinct attachment once.
+    pub fn encode(&self) -> Result<Vec<u8>, bincode::Error> {
+        let mut blobs = BTreeMap::new();
+        for_each_record(self, &mut |record| {
+            for file in record.files() {
+                blobs.entry(file.checksum).or_insert(&file.content[..]);
+            }
+        });
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        let image = VaultImageRef {
+            blobs,
+            root: &self.root,
+            trash: &self.trash,
+            templates: &self.templates,
+            members: &self.members,
+            removed_templates: &self.removed_templates,
+            removed_members: &self.removed_members,
+        };
+        bincode::serialize_into(&mut bytes, &image)?;
+        Ok(bytes)
+    }
+}
+
+impl VaultImage {
+    /// Vault with the content of every attachment.
+    pub(crate) fn into_vault(self) -> Result<Vault, bincode::Error> {
+        let mut vault = Vault {
+            root: self.root,
+            trash: self.trash,
+            templates: self.templates,
+            members: self.members,
+            removed_templates: self.removed_templates,
+            removed_members: self.removed_members,
+        };
+        attach_blobs(&mut vault, self.blobs)?;
+        Ok(vault)
+    }
+}
+
+/// Gives every attachment of the vault its content from `blobs`. Attachments with the
+/// same checksum share one buffer.
+fn attach_blobs(
+    vault: &mut Vault,
+    blobs: BTreeMap<[u8; 32], Vec<u8>>,
+) -> Result<(), bincode::Error> {
+    let blobs: BTreeMap<[u8; 32], Arc<[u8]>> = blobs
+        .into_iter()
+        .map(|(checksum, content)| (checksum, content.into()))
+        .collect();
+    let mut missing = false;
+    for_each_record_mut(vault, &mut |record| {
+        for file in record.files.iter_mut().flatten() {
+            match blobs.get(&file.checksum) {
+                Some(content) => file.content = content.clone(),
+                None => missing = true,
+            }
+        }
+    });
+    if missing {
+        return Err(bincode::Error::custom("attachment content missing from the vault"));
+    }
+    Ok(())
+}
+
+/// Calls `f` on every record of the vault, including the ones in the trash.
+fn for_each_record<'a>(vault: &'a Vault, f: &mut impl FnMut(&'a Record)) {
+    for_each_folder_record(&vault.root, f);
+    for entry in &vault.trash.entries {
+        match &entry.item {
+            TrashedItem::Record(record) => f(record),
+            TrashedItem::Folder(folder) => for_each_folder_record(folder, f),
+        }
+    }
+}
+
+fn for_each_folder_record<'a>(folder: &'a Folder, f: &mut impl FnMut(&'a Record)) {
+    folder.records.iter().for_each(&mut *f);
+    for subfolder in folder.subfolders.iter().flatten() {
+        for_each_folder_record(subfolder, f);
+    }
+}
+
+fn for_each_record_mut(vault: &mut Vault, f: &mut impl FnMut(&mut Record)) {
+    for_each_folder_record_mut(&mut vault.root, f);
+    for entry in &mut vault.trash.entries {
+        match &mut entry.item {
+            TrashedItem::Record(record) => f(record),
+            TrashedItem::Folder(folder) => for_each_folder_record_mut(folder, f),
+        }
+    }
+}
+
+fn for_each_folder_record_mut(folder: &mut Folder, f: &mut impl FnMut(&mut Record)) {
+    folder.records.iter_mut().for_each(&mut *f);
+    for subfolder in folder.subfolders.iter_mut().flatten() {
+        for_each_folder_record_mut(subfolder, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::sync::Arc;
+
+    use crate::models::{FieldKind, Folder, Record, RecordTemplate, Vault};
+
+    #[test]
+    fn test_identical_attachments_are_stored_once() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("ca.pem");
+        fs::write(&path, vec![42u8; 100_000]).unwrap();
+        let other = source.path().join("kubeconfig");
+        fs::write(&other, b"clusters: []").unwrap();
+
+        let mut root = Folder::new("main".into());
+        let mut subfolder = Folder::new("sub".into());
+        for _ in 0..10 {
+            let mut record = Record::new();
+            record.add_file(&path).unwrap();
+            subfolder.add_record(record);
+        }
+        let mut record = Record::new();
+        record.add_file(&other).unwrap();
+        root.add_record(record);
+        root.add_folder(subfolder);
+        let vault = Vault::new(root);
+
+        let bytes = vault.encode().unwrap();
+        assert!(bytes.len() < 2 * 100_000);
+
+        let decoded = Vault::decode(&bytes).unwrap();
+        assert_eq!(decoded, vault);
+        let records = &decoded.root.subfolders.as_ref().unwrap()[0].records;
+        assert!(Arc::ptr_eq(&records[0].files()[0].content, &records[9].files()[0].content));
+        assert_eq!(decoded.root.records[0].files()[0].content(), b"clusters: []");
+    }
+
+    #[test]
+    fn test_trash_is_stored_with_its_attachments() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("id_ed25519");
+        fs::write(&path, b"key").unwrap();
+        let mut root = Folder::new("main".into());
+        let mut record = Record::new();
+        record.add_file(&path).unwrap();
+        let record_id = record.id();
+        root.add_record(record);
+        let mut vault = Vault::new(root);
+        vault.trash_record(record_id).unwrap();
+        vault.set_trash_retention(7);
+
+        let decoded = Vault::decode(&vault.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded.trash(), vault.trash());
+        assert_eq!(decoded.trash().retention_days(), 7);
+    }
+
+    #[test]
+    fn test_templates_are_stored() {
+        let mut vault = Vault::new(Folder::new("main".into()));
+        vault.add_template(RecordTemplate::new("VPN", &[("server", FieldKind::Url)])).unwrap();
+
+        let decoded = Vault::decode(&vault.encode().unwrap()).unwrap();
+        assert_eq!(decoded.templates(), vault.templates());
+
+        let image = (BTreeMap::<[u8; 32], Vec<u8>>::new(), &vault.root, vault.trash());
+        let decoded = Vault::decode(&bincode::serialize(&image).unwrap()).unwrap();
+        assert_eq!(decoded.root.id(), vault.root.id());
+        assert!(decoded.templates().is_empty());
+    }
+}
@@ -1,16 +1,71 @@
-use std;
-
-use thiserror;
+use crate::models::FieldKind;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ModelsError {
     #[error("Field already exists")]
     FieldAlreadyExist,
 
+    #[error("Field name cannot be empty")]
+    EmptyFieldName,
+
+    #[error("Field not found")]
+    FieldNotFound,
+
     #[error("Error opening file")]
     FileReadError(#[from] std::io::Error),
 
     #[error("Error retrieving filename")]
     GetFilenameError,
 
+    #[error("File of {0} bytes exceeds the attachment size limit")]
+    FileTooLarge(u64),
+
+    #[error("Attachment not found")]
+    FileNotFound,
+
+    #[error("An attachment with this name already exists")]
+    AttachmentAlreadyExists,
+
+    #[error("Attachment content does not match its checksum")]
+    ChecksumMismatch,
+
+    #[error("Attachment name cannot be used as a file name")]
+    InvalidFilename,
+
+    #[error("{} already exists", .0.display())]
+    FileAlreadyExists(std::path::PathBuf),
+
+    #[error("Record version not found")]
+    VersionNotFound,
+
+    #[error("Record not found")]
+    RecordNotFound,
+
+    #[error("Folder not found")]
+    FolderNotFound,
+
+    #[error("Folder cannot be moved into itself or one of its subfolders")]
+    InvalidMove,
+
+    #[error("Invalid path {0}")]
+    InvalidPath(String),
+
+    #[error("Trash entry not found")]
+    TrashEntryNotFound,
+
+    #[error("A template with this name already exists")]
+    TemplateAlreadyExists,
+
+    #[error("Template not found")]
+    TemplateNotFound,
+
+    #[error("This public key is already a member of the vault")]
+    MemberAlreadyExists,
+
+    #[error("Member not found")]
+    MemberNotFound,
+
+    #[error("Invalid {0} value")]
+    InvalidFieldValue(FieldKind),
 }
+
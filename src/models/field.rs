@@ -0,0 +1,194 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use chrono::NaiveDate;
+use indexmap::IndexMap;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::models::ModelsError;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const MASK: &str = "••••••••";
+/// Most fields a field map reserves room for before they are read.
+const PREALLOCATED_FIELDS: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldKind {
+    Text,
+    Concealed,
+    Url,
+    Email,
+    Totp,
+    Note,
+    Date,
+}
+
+impl FieldKind {
+    pub const ALL: [FieldKind; 7] = [
+        FieldKind::Text,
+        FieldKind::Concealed,
+        FieldKind::Url,
+        FieldKind::Email,
+        FieldKind::Totp,
+        FieldKind::Note,
+        FieldKind::Date,
+    ];
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FieldKind::Text => "text",
+            FieldKind::Concealed => "secret",
+            FieldKind::Url => "url",
+            FieldKind::Email => "email",
+            FieldKind::Totp => "totp",
+            FieldKind::Note => "note",
+            FieldKind::Date => "date",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Value of a record field together with what kind of data it holds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Text(String),
+    Concealed(String),
+    Url(String),
+    Email(String),
+    Totp(String),
+    Note(String),
+    Date(NaiveDate),
+}
+
+impl FieldValue {
+    /// Builds a value of the given kind from its textual form, dates are `YYYY-MM-DD`.
+    pub fn new(kind: FieldKind, value: &str) -> Result<Self, ModelsError> {
+        let value = match kind {
+            FieldKind::Text => FieldValue::Text(value.into()),
+            FieldKind::Concealed => FieldValue::Concealed(value.into()),
+            FieldKind::Url => FieldValue::Url(value.into()),
+            FieldKind::Email => FieldValue::Email(value.into()),
+            FieldKind::Totp => FieldValue::Totp(value.into()),
+            FieldKind::Note => FieldValue::Note(value.into()),
+            FieldKind::Date => FieldValue::Date(
+                NaiveDate::parse_from_str(value.trim(), DATE_FORMAT)
+                    .map_err(|_| ModelsError::InvalidFieldValue(kind))?
+            ),
+        };
+        Ok(value)
+    }
+
+    pub fn kind(&self) -> FieldKind {
+        match self {
+            FieldValue::Text(_) => FieldKind::Text,
+            FieldValue::Concealed(_) => FieldKind::Concealed,
+            FieldValue::Url(_) => FieldKind::Url,
+            FieldValue::Email(_) => FieldKind::Email,
+            FieldValue::Totp(_) => FieldKind::Totp,
+            FieldValue::Note(_) => FieldKind::Note,
+            FieldValue::Date(_) => FieldKind::Date,
+        }
+    }
+
+    /// Secret values should be masked when displayed and never be searchable.
+    pub fn is_secret(&self) -> bool {
+        matches!(self, FieldValue::Concealed(_) | FieldValue::Totp(_))
+    }
+
+    /// Text to display, with secret values replaced by a mask.
+    pub fn masked(&self) -> String {
+        if self.is_secret() {
+            MASK.into()
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Text(value)
+            | FieldValue::Concealed(value)
+            | FieldValue::Url(value)
+            | FieldValue::Email(value)
+            | FieldValue::Totp(value)
+            | FieldValue::Note(value) => write!(f, "{}", value),
+            FieldValue::Date(date) => write!(f, "{}", date.format(DATE_FORMAT)),
+        }
+    }
+}
+
+/// Plain strings, e.g. fields of vaults written before values were typed, become text.
+impl From<String> for FieldValue {
+    fn from(value: String) -> Self {
+        FieldValue::Text(value)
+    }
+}
+
+impl From<&str> for FieldValue {
+    fn from(value: &str) -> Self {
+        FieldValue::Text(value.into())
+    }
+}
+
+/// Reads a map of fields without trusting the encoded length for its allocation, so
+/// that decoding bytes of another vault layout fails instead of aborting.
+pub(crate) fn deserialize_fields<'de, D, V>(deserializer: D) -> Result<IndexMap<String, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    struct FieldsVisitor<V>(PhantomData<V>);
+
+    impl<'de, V: Deserialize<'de>> Visitor<'de> for FieldsVisitor<V> {
+        type Value = IndexMap<String, V>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of fields")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let capacity = map.size_hint().unwrap_or(0).min(PREALLOCATED_FIELDS);
+            let mut fields = IndexMap::with_capacity(capacity);
+            while let Some((name, value)) = map.next_entry()? {
+                fields.insert(name, value);
+            }
+            Ok(fields)
+        }
+    }
+
+    deserializer.deserialize_map(FieldsVisitor(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::models::{FieldKind, FieldValue};
+
+    #[test]
+    fn test_field_value_from_text() {
+        for kind in FieldKind::ALL {
+            let text = if kind == FieldKind::Date { "2023-09-01" } else { "value" };
+            let value = FieldValue::new(kind, text).unwrap();
+            assert_eq!(value.kind(), kind);
+            assert_eq!(value.to_string(), text);
+        }
+        assert_eq!(
+            FieldValue::new(FieldKind::Date, " 2023-09-01 ").unwrap(),
+            FieldValue::Date(NaiveDate::from_ymd_opt(2023, 9, 1).unwrap()),
+        );
+        assert!(FieldValue::new(FieldKind::Date, "yesterday").is_err());
+    }
+
+    #[test]
+    fn test_masked() {
+        assert_eq!(FieldValue::Url("https://example.com".into()).masked(), "https://example.com");
+        assert_ne!(FieldValue::Concealed("hunter2".into()).masked(), "hunter2");
+        assert_ne!(FieldValue::Totp("JBSWY3DPEHPK3PXP".into()).masked(), "JBSWY3DPEHPK3PXP");
+    }
+}
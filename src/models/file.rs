@@ -0,0 +1,330 @@
+use std::ffi::{OsStr, OsString};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::{ModelsError, Record};
+
+/// Largest attachment accepted by [`Record::add_file`], in bytes.
+pub const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// An attachment of a record. Its content is not serialized with the record: it is
+/// stored once per vault in a blob table keyed by `checksum` (see [`Vault::encode`]),
+/// and records attaching identical content share it in memory.
+///
+/// [`Vault::encode`]: crate::models::Vault::encode
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordFile {
+    pub(crate) filename: OsString,
+    pub(crate) extension: Option<OsString>,
+    pub(crate) mime_type: String,
+    pub(crate) checksum: [u8; 32],
+    #[serde(skip)]
+    pub(crate) content: Arc<[u8]>,
+}
+
+impl RecordFile {
+    /// Reads the whole file in chunks, hashing it on the way.
+    /// Files without an extension such as `id_ed25519` or `Dockerfile` are accepted.
+    pub(crate) fn read(file_path: &Path) -> Result<Self, ModelsError> {
+        let filename = file_path.file_name().ok_or(ModelsError::GetFilenameError)?;
+        let file = File::open(file_path)?;
+        let size = file.metadata()?.len();
+        if size > MAX_FILE_SIZE {
+            return Err(ModelsError::FileTooLarge(size));
+        }
+
+        let mut reader = file.take(MAX_FILE_SIZE + 1);
+        let mut hasher = Sha256::new();
+        let mut content = Vec::with_capacity(size as usize);
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let read = reader.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&chunk[..read]);
+            content.extend_from_slice(&chunk[..read]);
+        }
+        if content.len() as u64 > MAX_FILE_SIZE {
+            return Err(ModelsError::FileTooLarge(content.len() as u64));
+        }
+
+        Ok(Self {
+            filename: filename.to_os_string(),
+            extension: file_path.extension().map(OsStr::to_os_string),
+            mime_type: mime_type(file_path),
+            checksum: hasher.finalize().into(),
+            content: content.into(),
+        })
+    }
+
+    /// Builds an attachment from content that is already in memory.
+    pub(crate) fn from_content(
+        filename: OsString,
+        extension: Option<OsString>,
+        content: Vec<u8>,
+    ) -> Self {
+        Self {
+            mime_type: mime_type(Path::new(&filename)),
+            checksum: Sha256::digest(&content).into(),
+            filename,
+            extension,
+            content: content.into(),
+        }
+    }
+
+    pub fn filename(&self) -> &OsStr {
+        &self.filename
+    }
+
+    pub fn extension(&self) -> Option<&OsStr> {
+        self.extension.as_deref()
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// SHA-256 of the content.
+    pub fn checksum(&self) -> [u8; 32] {
+        self.checksum
+    }
+
+    pub fn size(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+
+    fn verify(&self) -> Result<(), ModelsError> {
+        let checksum: [u8; 32] = Sha256::digest(&self.content).into();
+        if checksum != self.checksum {
+            return Err(ModelsError::ChecksumMismatch);
+        }
+        Ok(())
+    }
+}
+
+fn mime_type(file_path: &Path) -> String {
+    mime_guess::from_path(file_path)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string()
+}
+
+impl Record {
+    pub fn files(&self) -> &[RecordFile] {
+        self.files.as_deref().unwrap_or_default()
+    }
+
+    pub fn remove_file(&mut self, filename: &OsStr) -> Result<RecordFile, ModelsError> {
+        let files = self.files.as_mut().ok_or(ModelsError::FileNotFound)?;
+        let index = files
+            .iter()
+            .position(|f| f.filename == filename)
+            .ok_or(ModelsError::FileNotFound)?;
+        let file = files.remove(index);
+        if files.is_empty() {
+            self.files = None;
+        }
+        self.touch();
+        Ok(file)
+    }
+
+    /// Writes the attachment named `filename` into `directory` under its original name
+    /// and returns the path of the written file. Only the last component of the stored
+    /// name is used, so that a vault cannot make it write outside `directory`. An
+    /// existing file is only replaced if `overwrite` is set.
+    pub fn extract_file(
+        &self,
+        filename: &OsStr,
+        directory: &Path,
+        overwrite: bool,
+    ) -> Result<PathBuf, ModelsError> {
+        let file = self.files()
+            .iter()
+            .find(|f| f.filename == filename)
+            .ok_or(ModelsError::FileNotFound)?;
+        file.verify()?;
+        let stored = Path::new(&file.filename);
+        if stored.has_root() {
+            return Err(ModelsError::InvalidFilename);
+        }
+        let path = directory.join(stored.file_name().ok_or(ModelsError::InvalidFilename)?);
+        let mut options = OpenOptions::new();
+        if overwrite {
+            options.write(true).create(true).truncate(true);
+        } else {
+            options.write(true).create_new(true);
+        }
+        let mut target = options.open(&path).map_err(|error| match error.kind() {
+            ErrorKind::AlreadyExists => ModelsError::FileAlreadyExists(path.clone()),
+            _ => error.into(),
+        })?;
+        target.write_all(&file.content)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+
+    use sha2::{Digest, Sha256};
+
+    use crate::models::{ModelsError, Record, RecordFile, MAX_FILE_SIZE};
+
+    #[test]
+    fn test_add_and_extract_file() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("ca.pem");
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &content).unwrap();
+
+        let mut record = Record::new();
+        record.add_file(&path).unwrap();
+        let file = &record.files()[0];
+        assert_eq!(file.filename(), "ca.pem");
+        assert_eq!(file.extension(), Some("pem".as_ref()));
+        assert_eq!(file.size(), content.len());
+        assert_eq!(file.content(), &content[..]);
+        assert_eq!(file.checksum(), <[u8; 32]>::from(Sha256::digest(&content)));
+        assert_eq!(file.mime_type(), "application/x-x509-ca-cert");
+
+        let target = tempfile::tempdir().unwrap();
+        let extracted = record.extract_file("ca.pem".as_ref(), target.path(), false).unwrap();
+        assert_eq!(extracted, target.path().join("ca.pem"));
+        assert_eq!(fs::read(extracted).unwrap(), content);
+        assert!(matches!(
+            record.extract_file("missing".as_ref(), target.path(), false),
+            Err(ModelsError::FileNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_extract_file_stays_in_directory() {
+        let target = tempfile::tempdir().unwrap();
+        let directory = target.path().join("export");
+        fs::create_dir(&directory).unwrap();
+        let mut record = Record::new();
+        for name in ["../escape.txt", "nested/notes.txt", "..", "", "/etc/passwd"] {
+            let file = RecordFile::from_content(name.into(), None, b"content".to_vec());
+            record.files.get_or_insert_with(Vec::new).push(file);
+        }
+
+        let extracted = record.extract_file("../escape.txt".as_ref(), &directory, false).unwrap();
+        assert_eq!(extracted, directory.join("escape.txt"));
+        let extracted = record.extract_file("nested/notes.txt".as_ref(), &directory, false).unwrap();
+        assert_eq!(extracted, directory.join("notes.txt"));
+        for name in ["..", "", "/etc/passwd"] {
+            assert!(matches!(
+                record.extract_file(name.as_ref(), &directory, false),
+                Err(ModelsError::InvalidFilename)
+            ));
+        }
+        assert!(!target.path().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn test_extract_file_does_not_overwrite() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("notes.txt");
+        fs::write(&path, b"hello").unwrap();
+        let mut record = Record::new();
+        record.add_file(&path).unwrap();
+        fs::write(&path, b"newer notes").unwrap();
+
+        assert!(matches!(
+            record.extract_file("notes.txt".as_ref(), source.path(), false),
+            Err(ModelsError::FileAlreadyExists(existing)) if existing == path
+        ));
+        assert_eq!(fs::read(&path).unwrap(), b"newer notes");
+        record.extract_file("notes.txt".as_ref(), source.path(), true).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_add_file_without_extension() {
+        let source = tempfile::tempdir().unwrap();
+        let mut record = Record::new();
+        for name in ["id_ed25519", "Dockerfile", "kubeconfig"] {
+            let path = source.path().join(name);
+            fs::write(&path, name).unwrap();
+            record.add_file(&path).unwrap();
+        }
+
+        let files = record.files();
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|f| f.extension().is_none()));
+        assert_eq!(files[1].filename(), "Dockerfile");
+        assert_eq!(files[1].content(), b"Dockerfile");
+    }
+
+    #[test]
+    fn test_attachment_names_are_unique() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("notes.txt");
+        fs::write(&path, b"hello").unwrap();
+        let mut record = Record::new();
+        record.add_file(&path).unwrap();
+
+        let other = source.path().join("other");
+        fs::create_dir(&other).unwrap();
+        fs::write(other.join("notes.txt"), b"other notes").unwrap();
+        assert!(matches!(record.add_file(&other.join("notes.txt")), Err(ModelsError::AttachmentAlreadyExists)));
+        assert_eq!(record.files().len(), 1);
+        assert_eq!(record.files()[0].content(), b"hello");
+    }
+
+    #[test]
+    fn test_remove_file() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("notes.txt");
+        fs::write(&path, b"hello").unwrap();
+        let mut record = Record::new();
+        record.add_file(&path).unwrap();
+
+        let removed = record.remove_file("notes.txt".as_ref()).unwrap();
+        assert_eq!(removed.content(), b"hello");
+        assert!(record.files().is_empty());
+        assert!(matches!(record.remove_file("notes.txt".as_ref()), Err(ModelsError::FileNotFound)));
+    }
+
+    #[test]
+    fn test_extract_corrupted_file() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("notes.txt");
+        fs::write(&path, b"hello").unwrap();
+        let mut record = Record::new();
+        record.add_file(&path).unwrap();
+        record.files.as_mut().unwrap()[0].content = Arc::from(&b"jello"[..]);
+
+        let target = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            record.extract_file("notes.txt".as_ref(), target.path(), false),
+            Err(ModelsError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_file_too_large() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("big.bin");
+        let file = fs::File::create(&path).unwrap();
+        file.set_len(MAX_FILE_SIZE + 1).unwrap();
+
+        let mut record = Record::new();
+        assert!(matches!(record.add_file(&path), Err(ModelsError::FileTooLarge(_))));
+        assert!(record.files().is_empty());
+    }
+}
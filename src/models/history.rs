@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::models::field::deserialize_fields;
+use crate::models::{FieldValue, ModelsError, Record};
+
+/// How many previous versions of its fields a record keeps.
+pub const HISTORY_LIMIT: usize = 20;
+
+/// Snapshot of record fields as they were before an edit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordVersion {
+    #[serde(deserialize_with = "deserialize_fields")]
+    pub fields: IndexMap<String, FieldValue>,
+    pub modified: DateTime<Utc>,
+}
+
+impl Record {
+    /// Previous versions of the fields, oldest first.
+    pub fn history(&self) -> &[RecordVersion] {
+        &self.history
+    }
+
+    /// Brings back the fields of a previous version. The current fields are
+    /// kept in the history, so a restore can be undone as well.
+    pub fn restore(&mut self, version: usize) -> Result<(), ModelsError> {
+        let fields = match self.history.get(version) {
+            Some(version) => version.fields.clone(),
+            None => return Err(ModelsError::VersionNotFound),
+        };
+        self.set_fields(fields);
+        Ok(())
+    }
+
+    pub(crate) fn push_history(&mut self) {
+        self.push_version(RecordVersion {
+            fields: self.fields.clone(),
+            modified: self.modified,
+        });
+    }
+
+    /// Adds `version` as the newest entry of the history, dropping the oldest one
+    /// beyond [`HISTORY_LIMIT`].
+    pub(crate) fn push_version(&mut self, version: RecordVersion) {
+        self.history.push(version);
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+    }
+}
@@ -0,0 +1,805 @@
+//! Vault layouts written by earlier versions, oldest first, and the step that upgrades
+//! each of them to the next one (see [`migration`](super::migration)):
+//! the original one, before records and folders had ids, timestamps and typed fields,
+//! the ones that stored attachment content inline in every record, first with a
+//! required extension and then with an optional one, the blob table layouts from
+//! before records had titles, first without and then with a trash, the one from
+//! before vaults stored templates, format version 1, before they stored members, and
+//! format version 2, before templates and members had the time they were added and
+//! removed ones were remembered.
+//!
+//! These types are frozen, down to the field values, and never embed the current
+//! model: a change to the current model gets a new layout here instead of a change
+//! to an existing one.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsString;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::models::blob::VaultImage;
+use crate::models::{
+    FieldKind, FieldValue, Folder, Record, RecordFile, RecordTemplate, RecordVersion, Trash,
+    TrashEntry, TrashedItem, VaultMember,
+};
+
+#[derive(Deserialize)]
+pub(crate) struct LegacyRecordFile {
+    filename: OsString,
+    extension: OsString,
+    content: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LegacyRecord {
+    fields: HashMap<String, String>,
+    files: Option<Vec<LegacyRecordFile>>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LegacyFolder {
+    name: String,
+    records: Vec<LegacyRecord>,
+    subfolders: Option<Vec<LegacyFolder>>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct ExtensionRecordFile {
+    filename: OsString,
+    extension: OsString,
+    mime_type: String,
+    checksum: [u8; 32],
+    content: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct InlineRecordFile {
+    filename: OsString,
+    extension: Option<OsString>,
+    mime_type: String,
+    checksum: [u8; 32],
+    content: Vec<u8>,
+}
+
+/// Attachment whose content is stored in the blob table.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct StoredFile {
+    filename: OsString,
+    extension: Option<OsString>,
+    mime_type: String,
+    checksum: [u8; 32],
+}
+
+/// Field value of every layout since fields were typed.
+#[derive(Deserialize, Clone)]
+#[cfg_attr(test, derive(serde::Serialize))]
+enum StoredValue {
+    Text(String),
+    Concealed(String),
+    Url(String),
+    Email(String),
+    Totp(String),
+    Note(String),
+    Date(NaiveDate),
+}
+
+/// Field kind of the layouts with templates.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+enum StoredKind {
+    Text,
+    Concealed,
+    Url,
+    Email,
+    Totp,
+    Note,
+    Date,
+}
+
+/// Snapshot of record fields. Fields are read into a `Vec` rather than an `IndexMap`,
+/// which is encoded the same way but does not trust the encoded length for its
+/// allocation, so that bytes of another layout cannot make decoding abort.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct StoredVersion {
+    fields: Vec<(String, StoredValue)>,
+    modified: DateTime<Utc>,
+}
+
+/// Record of the layouts from before records had titles, generic over the
+/// attachment layout.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct UntitledRecord<F> {
+    id: Uuid,
+    fields: Vec<(String, StoredValue)>,
+    files: Option<Vec<F>>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+    history: Vec<StoredVersion>,
+    tags: BTreeSet<String>,
+    favorite: bool,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct UntitledFolder<F> {
+    id: Uuid,
+    name: String,
+    records: Vec<UntitledRecord<F>>,
+    subfolders: Option<Vec<UntitledFolder<F>>>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+}
+
+/// Record of the layouts with titles, up to format version 2.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct TitledRecord {
+    id: Uuid,
+    title: String,
+    icon: Option<String>,
+    url: Option<String>,
+    fields: Vec<(String, StoredValue)>,
+    files: Option<Vec<StoredFile>>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+    history: Vec<StoredVersion>,
+    tags: BTreeSet<String>,
+    favorite: bool,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct TitledFolder {
+    id: Uuid,
+    name: String,
+    records: Vec<TitledRecord>,
+    subfolders: Option<Vec<TitledFolder>>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+}
+
+/// Trash of the layouts that have one, generic over their records and folders.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+enum StoredTrashedItem<R, F> {
+    Record(R),
+    Folder(F),
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct StoredTrashEntry<R, F> {
+    item: StoredTrashedItem<R, F>,
+    deleted: DateTime<Utc>,
+    parent: Uuid,
+    path: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct StoredTrash<R, F> {
+    entries: Vec<StoredTrashEntry<R, F>>,
+    retention_days: u32,
+}
+
+type UntitledTrash = StoredTrash<UntitledRecord<StoredFile>, UntitledFolder<StoredFile>>;
+type TitledTrash = StoredTrash<TitledRecord, TitledFolder>;
+
+/// Blob table layout with a trash, from before records had titles.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct UntitledVaultImage {
+    blobs: BTreeMap<[u8; 32], Vec<u8>>,
+    root: UntitledFolder<StoredFile>,
+    trash: UntitledTrash,
+}
+
+/// Blob table layout from before vaults had a trash.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct VaultImageWithoutTrash {
+    blobs: BTreeMap<[u8; 32], Vec<u8>>,
+    root: UntitledFolder<StoredFile>,
+}
+
+/// Layout from before vaults stored templates.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct VaultImageWithoutTemplates {
+    blobs: BTreeMap<[u8; 32], Vec<u8>>,
+    root: TitledFolder,
+    trash: TitledTrash,
+}
+
+/// Template from before templates had the time they were added.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct UndatedTemplate {
+    name: String,
+    fields: Vec<(String, StoredKind)>,
+    attachments: Vec<String>,
+}
+
+/// Member from before members had the time they were added.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct UndatedMember {
+    label: String,
+    public_key: String,
+}
+
+/// Layout of format version 1, before vaults stored members.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct VaultImageV1 {
+    blobs: BTreeMap<[u8; 32], Vec<u8>>,
+    root: TitledFolder,
+    trash: TitledTrash,
+    templates: Vec<UndatedTemplate>,
+}
+
+/// Layout of format version 2, before templates and members had the time they were
+/// added and removed ones were remembered.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct VaultImageV2 {
+    blobs: BTreeMap<[u8; 32], Vec<u8>>,
+    root: TitledFolder,
+    trash: TitledTrash,
+    templates: Vec<UndatedTemplate>,
+    members: Vec<UndatedMember>,
+}
+
+impl<F> UntitledRecord<F> {
+    fn map_files<G>(self, f: &mut impl FnMut(F) -> G) -> UntitledRecord<G> {
+        UntitledRecord {
+            id: self.id,
+            fields: self.fields,
+            files: self.files.map(|files| files.into_iter().map(&mut *f).collect()),
+            created: self.created,
+            modified: self.modified,
+            history: self.history,
+            tags: self.tags,
+            favorite: self.favorite,
+        }
+    }
+}
+
+impl<F> UntitledFolder<F> {
+    fn map_files<G>(self, f: &mut impl FnMut(F) -> G) -> UntitledFolder<G> {
+        UntitledFolder {
+            id: self.id,
+            name: self.name,
+            records: self.records.into_iter().map(|r| r.map_files(f)).collect(),
+            subfolders: self.subfolders
+                .map(|subfolders| subfolders.into_iter().map(|s| s.map_files(f)).collect()),
+            created: self.created,
+            modified: self.modified,
+        }
+    }
+}
+
+/// Namespace of the ids given to records and folders of the original layout.
+const ORIGINAL_LAYOUT_NAMESPACE: Uuid = Uuid::from_u128(0x877c7179_dabb_453e_8113_1186b0a64ce2);
+
+/// Gives records and folders ids derived from their place in the tree, so that every
+/// machine upgrading the same vault gives them the same ids, and turns every field
+/// into text. Timestamps are unknown and set to the Unix epoch. Fields are sorted by
+/// name since the original layout did not keep their order.
+impl From<LegacyFolder> for UntitledFolder<ExtensionRecordFile> {
+    fn from(legacy: LegacyFolder) -> Self {
+        let path = legacy.name.clone();
+        upgrade_original(legacy, path)
+    }
+}
+
+/// Upgrades the folder found at `path`, made of the index and name of every folder
+/// below the root.
+fn upgrade_original(legacy: LegacyFolder, path: String) -> UntitledFolder<ExtensionRecordFile> {
+    let unknown = Utc.timestamp_opt(0, 0).unwrap();
+    let records = legacy.records.into_iter().enumerate().map(|(index, record)| {
+        let mut fields: Vec<(String, String)> = record.fields.into_iter().collect();
+        fields.sort();
+        UntitledRecord {
+            id: original_id(&format!("{}#{}", path, index)),
+            fields: fields.into_iter().map(|(name, value)| (name, StoredValue::Text(value))).collect(),
+            files: record.files
+                .map(|files| files.into_iter().map(ExtensionRecordFile::from).collect()),
+            created: unknown,
+            modified: unknown,
+            history: Vec::new(),
+            tags: BTreeSet::new(),
+            favorite: false,
+        }
+    });
+    let records = records.collect();
+    let subfolders = legacy.subfolders.map(|subfolders| {
+        subfolders
+            .into_iter()
+            .enumerate()
+            .map(|(index, subfolder)| {
+                let subfolder_path = format!("{}/{}:{}", path, index, subfolder.name);
+                upgrade_original(subfolder, subfolder_path)
+            })
+            .collect()
+    });
+    UntitledFolder {
+        id: original_id(&path),
+        name: legacy.name,
+        records,
+        subfolders,
+        created: unknown,
+        modified: unknown,
+    }
+}
+
+fn original_id(path: &str) -> Uuid {
+    Uuid::new_v5(&ORIGINAL_LAYOUT_NAMESPACE, path.as_bytes())
+}
+
+impl From<LegacyRecordFile> for ExtensionRecordFile {
+    fn from(file: LegacyRecordFile) -> Self {
+        let hashed = RecordFile::from_content(file.filename, None, file.content);
+        Self {
+            filename: hashed.filename,
+            extension: file.extension,
+            mime_type: hashed.mime_type,
+            checksum: hashed.checksum,
+            content: hashed.content.to_vec(),
+        }
+    }
+}
+
+impl From<UntitledFolder<ExtensionRecordFile>> for UntitledFolder<InlineRecordFile> {
+    fn from(folder: UntitledFolder<ExtensionRecordFile>) -> Self {
+        folder.map_files(&mut |file| InlineRecordFile {
+            filename: file.filename,
+            extension: Some(file.extension),
+            mime_type: file.mime_type,
+            checksum: file.checksum,
+            content: file.content,
+        })
+    }
+}
+
+/// Moves attachment content out of the records into the blob table.
+impl From<UntitledFolder<InlineRecordFile>> for VaultImageWithoutTrash {
+    fn from(folder: UntitledFolder<InlineRecordFile>) -> Self {
+        let mut blobs = BTreeMap::new();
+        let root = folder.map_files(&mut |file| {
+            blobs.entry(file.checksum).or_insert(file.content);
+            StoredFile {
+                filename: file.filename,
+                extension: file.extension,
+                mime_type: file.mime_type,
+                checksum: file.checksum,
+            }
+        });
+        Self { blobs, root }
+    }
+}
+
+impl From<VaultImageWithoutTrash> for UntitledVaultImage {
+    fn from(image: VaultImageWithoutTrash) -> Self {
+        Self {
+            blobs: image.blobs,
+            root: image.root,
+            trash: UntitledTrash {
+                entries: Vec::new(),
+                retention_days: Trash::default().retention_days,
+            },
+        }
+    }
+}
+
+impl<R, F> StoredTrash<R, F> {
+    fn map<S, G>(self, record: impl Fn(R) -> S, folder: impl Fn(F) -> G) -> StoredTrash<S, G> {
+        let entries = self.entries.into_iter().map(|entry| StoredTrashEntry {
+            item: match entry.item {
+                StoredTrashedItem::Record(r) => StoredTrashedItem::Record(record(r)),
+                StoredTrashedItem::Folder(f) => StoredTrashedItem::Folder(folder(f)),
+            },
+            deleted: entry.deleted,
+            parent: entry.parent,
+            path: entry.path,
+        });
+        StoredTrash {
+            entries: entries.collect(),
+            retention_days: self.retention_days,
+        }
+    }
+}
+
+/// A field conventionally named "title" or "name" becomes the record title.
+impl From<UntitledVaultImage> for VaultImageWithoutTemplates {
+    fn from(image: UntitledVaultImage) -> Self {
+        Self {
+            blobs: image.blobs,
+            root: image.root.into(),
+            trash: image.trash.map(TitledRecord::from, TitledFolder::from),
+        }
+    }
+}
+
+impl From<VaultImageWithoutTemplates> for VaultImageV1 {
+    fn from(image: VaultImageWithoutTemplates) -> Self {
+        Self {
+            blobs: image.blobs,
+            root: image.root,
+            trash: image.trash,
+            templates: Vec::new(),
+        }
+    }
+}
+
+impl From<VaultImageV1> for VaultImageV2 {
+    fn from(image: VaultImageV1) -> Self {
+        Self {
+            blobs: image.blobs,
+            root: image.root,
+            trash: image.trash,
+            templates: image.templates,
+            members: Vec::new(),
+        }
+    }
+}
+
+/// The time templates and members were added is unknown and set to the Unix epoch,
+/// so that a removal on any machine takes precedence.
+impl From<VaultImageV2> for VaultImage {
+    fn from(image: VaultImageV2) -> Self {
+        let unknown = Utc.timestamp_opt(0, 0).unwrap();
+        let trash = image.trash.map(Record::from, Folder::from);
+        let entries = trash.entries.into_iter().map(|entry| TrashEntry {
+            item: match entry.item {
+                StoredTrashedItem::Record(record) => TrashedItem::Record(record),
+                StoredTrashedItem::Folder(folder) => TrashedItem::Folder(folder),
+            },
+            deleted: entry.deleted,
+            parent: entry.parent,
+            path: entry.path,
+        });
+        Self {
+            blobs: image.blobs,
+            root: image.root.into(),
+            trash: Trash {
+                entries: entries.collect(),
+                retention_days: trash.retention_days,
+            },
+            templates: image.templates
+                .into_iter()
+                .map(|template| RecordTemplate {
+                    name: template.name,
+                    fields: template.fields.into_iter().map(|(name, kind)| (name, kind.into())).collect(),
+                    attachments: template.attachments,
+                    added: unknown,
+                })
+                .collect(),
+            members: image.members
+                .into_iter()
+                .map(|member| VaultMember {
+                    label: member.label,
+                    public_key: member.public_key,
+                    added: unknown,
+                })
+                .collect(),
+            removed_templates: Vec::new(),
+            removed_members: Vec::new(),
+        }
+    }
+}
+
+/// Attachment without its content, which is filled in from the blob table.
+impl From<StoredFile> for RecordFile {
+    fn from(file: StoredFile) -> Self {
+        Self {
+            filename: file.filename,
+            extension: file.extension,
+            mime_type: file.mime_type,
+            checksum: file.checksum,
+            content: Vec::new().into(),
+        }
+    }
+}
+
+impl From<UntitledRecord<StoredFile>> for TitledRecord {
+    fn from(record: UntitledRecord<StoredFile>) -> Self {
+        Self {
+            id: record.id,
+            title: conventional_title(&record.fields),
+            icon: None,
+            url: None,
+            fields: record.fields,
+            files: record.files,
+            created: record.created,
+            modified: record.modified,
+            history: record.history,
+            tags: record.tags,
+            favorite: record.favorite,
+        }
+    }
+}
+
+impl From<UntitledFolder<StoredFile>> for TitledFolder {
+    fn from(folder: UntitledFolder<StoredFile>) -> Self {
+        Self {
+            id: folder.id,
+            name: folder.name,
+            records: folder.records.into_iter().map(TitledRecord::from).collect(),
+            subfolders: folder.subfolders
+                .map(|subfolders| subfolders.into_iter().map(TitledFolder::from).collect()),
+            created: folder.created,
+            modified: folder.modified,
+        }
+    }
+}
+
+impl From<TitledRecord> for Record {
+    fn from(record: TitledRecord) -> Self {
+        Self {
+            id: record.id,
+            title: record.title,
+            icon: record.icon,
+            url: record.url,
+            fields: stored_fields(record.fields),
+            files: record.files
+                .map(|files| files.into_iter().map(RecordFile::from).collect()),
+            created: record.created,
+            modified: record.modified,
+            history: record.history.into_iter().map(RecordVersion::from).collect(),
+            tags: record.tags,
+            favorite: record.favorite,
+        }
+    }
+}
+
+impl From<TitledFolder> for Folder {
+    fn from(folder: TitledFolder) -> Self {
+        Self {
+            id: folder.id,
+            name: folder.name,
+            records: folder.records.into_iter().map(Record::from).collect(),
+            subfolders: folder.subfolders
+                .map(|subfolders| subfolders.into_iter().map(Folder::from).collect()),
+            created: folder.created,
+            modified: folder.modified,
+        }
+    }
+}
+
+impl From<StoredVersion> for RecordVersion {
+    fn from(version: StoredVersion) -> Self {
+        Self {
+            fields: stored_fields(version.fields),
+            modified: version.modified,
+        }
+    }
+}
+
+impl From<StoredValue> for FieldValue {
+    fn from(value: StoredValue) -> Self {
+        match value {
+            StoredValue::Text(value) => FieldValue::Text(value),
+            StoredValue::Concealed(value) => FieldValue::Concealed(value),
+            StoredValue::Url(value) => FieldValue::Url(value),
+            StoredValue::Email(value) => FieldValue::Email(value),
+            StoredValue::Totp(value) => FieldValue::Totp(value),
+            StoredValue::Note(value) => FieldValue::Note(value),
+            StoredValue::Date(date) => FieldValue::Date(date),
+        }
+    }
+}
+
+impl From<StoredKind> for FieldKind {
+    fn from(kind: StoredKind) -> Self {
+        match kind {
+            StoredKind::Text => FieldKind::Text,
+            StoredKind::Concealed => FieldKind::Concealed,
+            StoredKind::Url => FieldKind::Url,
+            StoredKind::Email => FieldKind::Email,
+            StoredKind::Totp => FieldKind::Totp,
+            StoredKind::Note => FieldKind::Note,
+            StoredKind::Date => FieldKind::Date,
+        }
+    }
+}
+
+fn stored_fields(fields: Vec<(String, StoredValue)>) -> IndexMap<String, FieldValue> {
+    fields.into_iter().map(|(name, value)| (name, value.into())).collect()
+}
+
+fn conventional_title(fields: &[(String, StoredValue)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| (name, FieldValue::from(value.clone())))
+        .find(|(name, value)| {
+            matches!(name.to_lowercase().as_str(), "title" | "name") && !value.is_secret()
+        })
+        .map(|(_, value)| value.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashMap};
+    use std::sync::Arc;
+
+    use serde::Serialize;
+
+    use super::{
+        ExtensionRecordFile, InlineRecordFile, StoredFile, StoredTrashEntry, StoredTrashedItem,
+        StoredValue, UntitledFolder, UntitledRecord, UntitledTrash, UntitledVaultImage,
+        VaultImageWithoutTrash,
+    };
+    use crate::models::{FieldValue, Folder, Record, RecordFile, TrashedItem, Vault};
+
+    #[derive(Serialize)]
+    struct OldRecord {
+        fields: HashMap<String, String>,
+        files: Option<Vec<()>>,
+    }
+
+    #[derive(Serialize)]
+    struct OldFolder {
+        name: String,
+        records: Vec<OldRecord>,
+        subfolders: Option<Vec<OldFolder>>,
+    }
+
+    #[test]
+    fn test_decode_legacy_folder() {
+        let mut fields = HashMap::new();
+        fields.insert("password".to_string(), "hunter2".to_string());
+        let old = OldFolder {
+            name: "main".into(),
+            records: Vec::new(),
+            subfolders: Some(vec![
+                OldFolder {
+                    name: "sub".into(),
+                    records: vec![OldRecord { fields, files: None }],
+                    subfolders: None,
+                },
+                OldFolder { name: "sub".into(), records: Vec::new(), subfolders: None },
+            ]),
+        };
+        let bytes = bincode::serialize(&old).unwrap();
+
+        let folder = Vault::decode(&bytes).unwrap().root;
+
+        assert_eq!(folder.name, "main");
+        let subfolders = folder.subfolders.as_ref().unwrap();
+        assert_eq!(subfolders[0].name, "sub");
+        assert_eq!(subfolders[0].records[0].fields["password"], FieldValue::Text("hunter2".into()));
+        assert_ne!(subfolders[0].id(), subfolders[1].id());
+        // Every machine upgrading the vault gets the same ids and timestamps.
+        assert_eq!(Vault::decode(&bytes).unwrap().root, folder);
+    }
+
+    fn untitled_folder<F>(folder: &Folder, files: Vec<F>) -> UntitledFolder<F> {
+        let record = Record::new();
+        UntitledFolder {
+            id: folder.id,
+            name: folder.name.clone(),
+            records: vec![UntitledRecord {
+                id: record.id,
+                fields: Vec::new(),
+                files: Some(files),
+                created: record.created,
+                modified: record.modified,
+                history: Vec::new(),
+                tags: BTreeSet::new(),
+                favorite: true,
+            }],
+            subfolders: None,
+            created: folder.created,
+            modified: folder.modified,
+        }
+    }
+
+    #[test]
+    fn test_decode_folder_with_required_extensions() {
+        let folder = Folder::new("main".into());
+        let old = untitled_folder(&folder, vec![ExtensionRecordFile {
+            filename: "ca.pem".into(),
+            extension: "pem".into(),
+            mime_type: "application/x-x509-ca-cert".into(),
+            checksum: [7; 32],
+            content: b"cert".to_vec(),
+        }]);
+        let bytes = bincode::serialize(&old).unwrap();
+
+        let decoded = Vault::decode(&bytes).unwrap().root;
+
+        assert_eq!(decoded.id(), folder.id());
+        let record = &decoded.records[0];
+        assert!(record.is_favorite());
+        let file = &record.files()[0];
+        assert_eq!(file.filename(), "ca.pem");
+        assert_eq!(file.extension(), Some("pem".as_ref()));
+        assert_eq!(file.checksum(), [7; 32]);
+        assert_eq!(file.content(), b"cert");
+    }
+
+    #[test]
+    fn test_decode_folder_with_inline_files() {
+        let folder = Folder::new("main".into());
+        let file = |filename: &str| InlineRecordFile {
+            filename: filename.into(),
+            extension: None,
+            mime_type: "application/octet-stream".into(),
+            checksum: [7; 32],
+            content: b"config".to_vec(),
+        };
+        let old = untitled_folder(&folder, vec![file("kubeconfig"), file("kubeconfig-copy")]);
+        let bytes = bincode::serialize(&old).unwrap();
+
+        let decoded = Vault::decode(&bytes).unwrap().root;
+
+        let files = decoded.records[0].files();
+        assert_eq!(files[0].filename(), "kubeconfig");
+        assert_eq!(files[0].extension(), None);
+        assert_eq!(files[1].content(), b"config");
+        assert!(Arc::ptr_eq(&files[0].content, &files[1].content));
+    }
+
+    #[test]
+    fn test_decode_untitled_images() {
+        let folder = Folder::new("main".into());
+        let content = RecordFile::from_content("kubeconfig".into(), None, b"config".to_vec());
+        let file = || StoredFile {
+            filename: "kubeconfig".into(),
+            extension: None,
+            mime_type: content.mime_type().into(),
+            checksum: content.checksum(),
+        };
+        let mut root = untitled_folder(&folder, vec![file()]);
+        root.records[0].fields.push(("Name".into(), StoredValue::Text("prod cluster".into())));
+        let deleted = untitled_folder(&folder, vec![file()]);
+        let image = UntitledVaultImage {
+            blobs: [(content.checksum(), b"config".to_vec())].into(),
+            root,
+            trash: UntitledTrash {
+                entries: vec![StoredTrashEntry {
+                    item: StoredTrashedItem::Record(deleted.records.into_iter().next().unwrap()),
+                    deleted: folder.created(),
+                    parent: folder.id(),
+                    path: Vec::new(),
+                }],
+                retention_days: 3,
+            },
+        };
+
+        let decoded = Vault::decode(&bincode::serialize(&image).unwrap()).unwrap();
+
+        assert_eq!(decoded.root.records[0].title(), "prod cluster");
+        assert_eq!(decoded.root.records[0].files()[0].content(), b"config");
+        assert_eq!(decoded.trash().retention_days(), 3);
+        let TrashedItem::Record(record) = &decoded.trash().entries()[0].item else {
+            panic!("expected a trashed record");
+        };
+        assert_eq!(record.files()[0].content(), b"config");
+
+        let image = VaultImageWithoutTrash {
+            blobs: [(content.checksum(), b"config".to_vec())].into(),
+            root: untitled_folder(&folder, vec![file()]),
+        };
+        let decoded = Vault::decode(&bincode::serialize(&image).unwrap()).unwrap();
+        assert_eq!(decoded.root.records[0].title(), "");
+        assert!(decoded.trash().entries().is_empty());
+    }
+
+    #[test]
+    fn test_decode_current_folder() {
+        let vault = Vault::new(Folder::new("main".into()));
+        let bytes = vault.encode().unwrap();
+        assert_eq!(Vault::decode(&bytes).unwrap(), vault);
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::vault::record_removal;
+use crate::models::{ModelsError, Vault};
+
+/// Someone a team vault is encrypted to, identified by their public key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VaultMember {
+    pub label: String,
+    pub public_key: String,
+    pub(crate) added: DateTime<Utc>,
+}
+
+impl Vault {
+    /// Members of the vault.
+    pub fn members(&self) -> &[VaultMember] {
+        &self.members
+    }
+
+    /// Adds a member, each public key can only be added once.
+    pub fn add_member(&mut self, label: &str, public_key: &str) -> Result<(), ModelsError> {
+        let public_key = public_key.trim();
+        if self.members.iter().any(|m| m.public_key == public_key) {
+            return Err(ModelsError::MemberAlreadyExists);
+        }
+        self.members.push(VaultMember {
+            label: label.trim().to_string(),
+            public_key: public_key.to_string(),
+            added: Utc::now(),
+        });
+        self.root.touch();
+        Ok(())
+    }
+
+    /// Removes the member with `public_key`. Copies of the vault encrypted before the
+    /// removal can still be opened by them.
+    pub fn remove_member(&mut self, public_key: &str) -> Result<VaultMember, ModelsError> {
+        let index = self.members
+            .iter()
+            .position(|m| m.public_key == public_key)
+            .ok_or(ModelsError::MemberNotFound)?;
+        record_removal(&mut self.removed_members, public_key);
+        self.root.touch();
+        Ok(self.members.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{Folder, ModelsError, Vault};
+
+    #[test]
+    fn test_add_and_remove_members() {
+        let mut vault = Vault::new(Folder::new("team".into()));
+        vault.add_member(" Alice ", "age1alice").unwrap();
+        vault.add_member("Bob", "age1bob").unwrap();
+        assert!(matches!(vault.add_member("Alice again", "age1alice "), Err(ModelsError::MemberAlreadyExists)));
+        assert_eq!(vault.members()[0].label, "Alice");
+
+        let removed = vault.remove_member("age1alice").unwrap();
+        assert_eq!(removed.label, "Alice");
+        assert_eq!(vault.members().len(), 1);
+        assert!(matches!(vault.remove_member("age1alice"), Err(ModelsError::MemberNotFound)));
+    }
+}
@@ -0,0 +1,285 @@
+//! Reading vaults written by any version of nordstone.
+//!
+//! Vaults start with [`MAGIC`] followed by the little-endian format version. Vaults
+//! written before the header was introduced are recognized by trying every headerless
+//! layout, newest first. A layout only matches if it accounts for every byte, and the
+//! tests check that each fixture matches its own layout only. Whatever layout the
+//! bytes are in, they are upgraded one step at a time through every later layout
+//! up to the current one, so that adding a format version only takes a new layout and
+//! the step from the previous one.
+
+use bincode::Options;
+use serde::de::{DeserializeOwned, Error};
+
+use crate::models::blob::VaultImage;
+use crate::models::legacy::{
+    ExtensionRecordFile, InlineRecordFile, LegacyFolder, UntitledFolder, UntitledVaultImage,
+    VaultImageV1, VaultImageV2, VaultImageWithoutTemplates, VaultImageWithoutTrash,
+};
+use crate::models::Vault;
+
+pub(crate) const MAGIC: &[u8; 4] = b"NRDS";
+pub(crate) const FORMAT_VERSION: u16 = 3;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Every vault layout, oldest first.
+enum Layout {
+    Original(LegacyFolder),
+    RequiredExtension(UntitledFolder<ExtensionRecordFile>),
+    InlineFiles(UntitledFolder<InlineRecordFile>),
+    BlobTable(VaultImageWithoutTrash),
+    Trash(UntitledVaultImage),
+    Titles(VaultImageWithoutTemplates),
+    V1(VaultImageV1),
+    V2(VaultImageV2),
+    Current(VaultImage),
+}
+
+impl Layout {
+    fn read(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        if !bytes.starts_with(MAGIC) {
+            return Self::read_headerless(bytes);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(bincode::Error::custom("vault header truncated"));
+        }
+        let version = u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+        let payload = &bytes[HEADER_LEN..];
+        match version {
+            1 => deserialize(payload, Self::V1),
+            2 => deserialize(payload, Self::V2),
+            FORMAT_VERSION => deserialize(payload, Self::Current),
+            version if version > FORMAT_VERSION => Err(bincode::Error::custom(format!(
+                "vault format version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            ))),
+            version => Err(bincode::Error::custom(format!("unknown vault format version {}", version))),
+        }
+    }
+
+    fn read_headerless(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        deserialize(bytes, Self::V1)
+            .or_else(|_| deserialize(bytes, Self::Titles))
+            .or_else(|_| deserialize(bytes, Self::Trash))
+            .or_else(|_| deserialize(bytes, Self::BlobTable))
+            .or_else(|_| deserialize(bytes, Self::InlineFiles))
+            .or_else(|_| deserialize(bytes, Self::RequiredExtension))
+            .or_else(|_| deserialize(bytes, Self::Original))
+    }
+
+    /// The same vault in the next newer layout.
+    fn upgrade(self) -> Self {
+        match self {
+            Self::Original(folder) => Self::RequiredExtension(folder.into()),
+            Self::RequiredExtension(folder) => Self::InlineFiles(folder.into()),
+            Self::InlineFiles(folder) => Self::BlobTable(folder.into()),
+            Self::BlobTable(image) => Self::Trash(image.into()),
+            Self::Trash(image) => Self::Titles(image.into()),
+            Self::Titles(image) => Self::V1(image.into()),
+            Self::V1(image) => Self::V2(image.into()),
+            Self::V2(image) => Self::Current(image.into()),
+            Self::Current(image) => Self::Current(image),
+        }
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(
+    bytes: &[u8],
+    layout: impl FnOnce(T) -> Layout,
+) -> Result<Layout, bincode::Error> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(bytes)
+        .map(layout)
+}
+
+impl Vault {
+    /// Decodes a vault written by [`Vault::encode`] of this or any earlier version,
+    /// upgrading older layouts to the current one.
+    pub fn decode(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        let mut layout = Layout::read(bytes)?;
+        loop {
+            match layout {
+                Layout::Current(image) => return image.into_vault(),
+                older => layout = older.upgrade(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+
+    use super::{deserialize, Layout, FORMAT_VERSION};
+    use crate::models::{FieldKind, FieldValue, Folder, Record, TrashedItem, Vault};
+
+    const CERTIFICATE: &[u8] = b"-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+
+    /// Vault written by an earlier version, see `tests/fixtures`.
+    fn fixture(name: &str) -> Vault {
+        let bytes = fs::read(format!("tests/fixtures/vault-{}.bin", name)).unwrap();
+        Vault::decode(&bytes).unwrap()
+    }
+
+    fn infra(root: &Folder) -> &Folder {
+        &root.subfolders.as_ref().unwrap()[0]
+    }
+
+    /// Checks the tree every fixture was written with: "prod-root" with a password
+    /// and a certificate in the root folder, and a deploy record attaching the same
+    /// certificate in "Infra".
+    fn assert_sample_tree(root: &Folder) -> (&Record, &Record) {
+        assert_eq!(root.name, "main");
+        let record = &root.records[0];
+        assert_eq!(record.fields["login"].to_string(), "admin");
+        assert_eq!(record.fields["password"].to_string(), "hunter2");
+        assert_eq!(record.files()[0].filename(), "ca.pem");
+        assert_eq!(record.files()[0].extension(), Some("pem".as_ref()));
+
+        let infra = infra(root);
+        assert_eq!(infra.name, "Infra");
+        let deploy = &infra.records[0];
+        assert_eq!(deploy.fields["url"].to_string(), "https://ci.example.com");
+        assert_eq!(deploy.files()[0].checksum(), record.files()[0].checksum());
+        assert!(Arc::ptr_eq(&deploy.files()[0].content, &record.files()[0].content));
+        (record, deploy)
+    }
+
+    /// Checks the parts of the sample tree written since typed fields, tags and
+    /// attachments without an extension.
+    fn assert_typed_tree(root: &Folder) {
+        let (record, deploy) = assert_sample_tree(root);
+        assert_eq!(record.files()[0].content(), CERTIFICATE);
+        assert_eq!(record.fields["password"].kind(), FieldKind::Concealed);
+        assert!(record.is_favorite());
+        assert!(record.tags().contains("prod"));
+        assert_eq!(deploy.fields["url"].kind(), FieldKind::Url);
+    }
+
+    fn assert_kubeconfig(root: &Folder) {
+        let kubeconfig = &infra(root).records[0].files()[1];
+        assert_eq!(kubeconfig.filename(), "kubeconfig");
+        assert_eq!(kubeconfig.extension(), None);
+        assert_eq!(kubeconfig.content(), b"clusters: []\n");
+    }
+
+    fn assert_trashed_record(vault: &Vault) {
+        let TrashedItem::Record(record) = &vault.trash().entries()[0].item else {
+            panic!("expected a trashed record");
+        };
+        assert_eq!(record.fields["login"].to_string(), "old-admin");
+        assert_eq!(record.files()[0].content(), CERTIFICATE);
+    }
+
+    #[test]
+    fn test_decode_original_fixture() {
+        let vault = fixture("baseline");
+        let (record, _) = assert_sample_tree(&vault.root);
+        // The original version never read attachment content.
+        assert!(record.files()[0].content().is_empty());
+        assert_eq!(record.fields["password"], FieldValue::Text("hunter2".into()));
+        assert_eq!(record.title(), "prod-root");
+        assert!(vault.trash().entries().is_empty());
+    }
+
+    #[test]
+    fn test_decode_inline_fixtures() {
+        let vault = fixture("inline-required-extension");
+        assert_typed_tree(&vault.root);
+        assert_eq!(vault.root.records[0].title(), "prod-root");
+
+        let vault = fixture("inline-optional-extension");
+        assert_typed_tree(&vault.root);
+        assert_kubeconfig(&vault.root);
+    }
+
+    #[test]
+    fn test_decode_blob_table_fixtures() {
+        let vault = fixture("blob-table");
+        assert_typed_tree(&vault.root);
+        assert_kubeconfig(&vault.root);
+        assert!(vault.trash().entries().is_empty());
+
+        let vault = fixture("blob-table-trash");
+        assert_typed_tree(&vault.root);
+        assert_trashed_record(&vault);
+        assert_eq!(vault.root.records[0].title(), "prod-root");
+    }
+
+    #[test]
+    fn test_decode_titled_fixtures() {
+        for name in ["titles", "templates"] {
+            let vault = fixture(name);
+            assert_typed_tree(&vault.root);
+            assert_kubeconfig(&vault.root);
+            assert_trashed_record(&vault);
+            assert_eq!(vault.root.records[0].title(), "AWS prod root");
+        }
+        assert!(fixture("titles").templates().is_empty());
+        assert_eq!(fixture("templates").find_template("VPN").unwrap().fields["server"], FieldKind::Url);
+    }
+
+    #[test]
+    fn test_decode_versioned_fixtures() {
+        for name in ["v1", "v2"] {
+            let vault = fixture(name);
+            assert_typed_tree(&vault.root);
+            assert_kubeconfig(&vault.root);
+            assert_trashed_record(&vault);
+            assert_eq!(vault.find_template("VPN").unwrap().fields["server"], FieldKind::Url);
+        }
+        assert!(fixture("v1").members().is_empty());
+        let vault = fixture("v2");
+        assert_eq!(vault.members()[0].label, "Alice");
+        assert_eq!(vault.members()[0].added, vault.templates()[0].added);
+    }
+
+    /// Names of the headerless layouts that `bytes` can be read as.
+    fn matching_layouts(bytes: &[u8]) -> Vec<&'static str> {
+        let layouts = [
+            ("original", deserialize(bytes, Layout::Original).is_ok()),
+            ("required extension", deserialize(bytes, Layout::RequiredExtension).is_ok()),
+            ("inline files", deserialize(bytes, Layout::InlineFiles).is_ok()),
+            ("blob table", deserialize(bytes, Layout::BlobTable).is_ok()),
+            ("trash", deserialize(bytes, Layout::Trash).is_ok()),
+            ("titles", deserialize(bytes, Layout::Titles).is_ok()),
+            ("v1", deserialize(bytes, Layout::V1).is_ok()),
+            ("v2", deserialize(bytes, Layout::V2).is_ok()),
+            ("current", deserialize(bytes, Layout::Current).is_ok()),
+        ];
+        layouts.into_iter().filter(|(_, matches)| *matches).map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_fixtures_match_only_their_layout() {
+        let fixtures = [
+            ("baseline", "original"),
+            ("inline-required-extension", "required extension"),
+            ("inline-optional-extension", "inline files"),
+            ("blob-table", "blob table"),
+            ("blob-table-trash", "trash"),
+            ("titles", "titles"),
+            ("templates", "v1"),
+        ];
+        for (name, layout) in fixtures {
+            let bytes = fs::read(format!("tests/fixtures/vault-{}.bin", name)).unwrap();
+            assert_eq!(matching_layouts(&bytes), [layout], "{}", name);
+        }
+        for name in ["v1", "v2"] {
+            let bytes = fs::read(format!("tests/fixtures/vault-{}.bin", name)).unwrap();
+            assert_eq!(matching_layouts(&bytes[6..]), [name]);
+        }
+    }
+
+    #[test]
+    fn test_reject_unknown_versions() {
+        let mut bytes = Vault::new(Folder::new("main".into())).encode().unwrap();
+        bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let error = Vault::decode(&bytes).unwrap_err();
+        assert!(error.to_string().contains("newer"));
+        assert!(Vault::decode(b"NRDS\x02").is_err());
+    }
+}
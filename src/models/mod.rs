@@ -1,126 +1,331 @@
+mod blob;
 mod errors;
+mod field;
+mod file;
+mod history;
+mod legacy;
+mod member;
+mod migration;
+mod path;
+mod search;
+mod template;
+mod trash;
+mod tree;
+mod vault;
 
-use std::collections::HashMap;
-use std::ffi::OsString;
-use std::fs::File;
-use std::io::Read;
+use std::collections::BTreeSet;
 use std::path::Path;
 
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use errors::ModelsError;
+pub use errors::ModelsError;
+pub use field::{FieldKind, FieldValue};
+pub use file::{RecordFile, MAX_FILE_SIZE};
+pub use history::{RecordVersion, HISTORY_LIMIT};
+pub use member::VaultMember;
+pub use path::{escape_name, PathEntry, VaultPath};
+pub use search::{RecordFilter, RecordPath};
+pub use template::RecordTemplate;
+pub use trash::{Trash, TrashEntry, TrashedItem, DEFAULT_TRASH_RETENTION_DAYS};
+pub use vault::{Removed, Vault};
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct RecordFile {
-    filename: OsString,
-    extension: OsString,
-    content: Vec<u8>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Record {
-    pub fields: HashMap<String, String>,
+    pub(crate) id: Uuid,
+    pub(crate) title: String,
+    pub(crate) icon: Option<String>,
+    pub(crate) url: Option<String>,
+    #[serde(deserialize_with = "field::deserialize_fields")]
+    pub fields: IndexMap<String, FieldValue>,
     pub(crate) files: Option<Vec<RecordFile>>,
+    pub(crate) created: DateTime<Utc>,
+    pub(crate) modified: DateTime<Utc>,
+    pub(crate) history: Vec<RecordVersion>,
+    pub(crate) tags: BTreeSet<String>,
+    pub(crate) favorite: bool,
 }
 
 impl Record {
     pub fn new() -> Self {
+        let now = Utc::now();
         Self {
-            fields: HashMap::new(),
+            id: Uuid::new_v4(),
+            title: String::new(),
+            icon: None,
+            url: None,
+            fields: IndexMap::new(),
             files: None,
+            created: now,
+            modified: now,
+            history: Vec::new(),
+            tags: BTreeSet::new(),
+            favorite: false,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        let title = title.trim();
+        if self.title != title {
+            self.title = title.to_string();
+            self.touch();
+        }
+    }
+
+    /// Name shown wherever records are listed: the title, or the first non-secret
+    /// field value for records without one.
+    pub fn display_name(&self) -> String {
+        self.path_name().unwrap_or_else(|| "untitled".to_string())
+    }
+
+    /// Short text or emoji shown next to the title.
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    pub fn set_icon(&mut self, icon: Option<String>) {
+        let icon = icon.filter(|i| !i.trim().is_empty());
+        if self.icon != icon {
+            self.icon = icon;
+            self.touch();
+        }
+    }
+
+    /// Website the record belongs to.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn set_url(&mut self, url: Option<String>) {
+        let url = url.filter(|u| !u.trim().is_empty());
+        if self.url != url {
+            self.url = url;
+            self.touch();
+        }
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    /// Adds a tag, surrounding whitespace is dropped and empty tags are ignored.
+    pub fn add_tag(&mut self, tag: &str) {
+        let tag = tag.trim();
+        if !tag.is_empty() && self.tags.insert(tag.to_string()) {
+            self.touch();
+        }
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        if self.tags.remove(tag) {
+            self.touch();
         }
     }
 
-    pub fn add_field(&mut self, field_name: String, value: String) -> Result<(), ModelsError> {
+    pub fn set_tags(&mut self, tags: BTreeSet<String>) {
+        let tags = tags
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(String::from)
+            .collect();
+        if self.tags != tags {
+            self.tags = tags;
+            self.touch();
+        }
+    }
+
+    pub fn is_favorite(&self) -> bool {
+        self.favorite
+    }
+
+    pub fn set_favorite(&mut self, favorite: bool) {
+        if self.favorite != favorite {
+            self.favorite = favorite;
+            self.touch();
+        }
+    }
+
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn touch(&mut self) {
+        self.modified = Utc::now();
+    }
+
+    pub fn add_field(&mut self, field_name: String, value: FieldValue) -> Result<(), ModelsError> {
+        if field_name.is_empty() {
+            return Err(ModelsError::EmptyFieldName);
+        }
         if self.fields.contains_key(&field_name) {
             return Err(ModelsError::FieldAlreadyExist);
         }
         self.fields.insert(field_name, value);
+        self.touch();
         Ok(())
     }
 
+    /// Replaces all fields at once, e.g. after they were edited in the GUI.
+    /// The previous fields are kept in the history.
+    pub fn set_fields(&mut self, fields: IndexMap<String, FieldValue>) {
+        if self.fields.iter().ne(fields.iter()) {
+            self.push_history();
+            self.fields = fields;
+            self.touch();
+        }
+    }
+
+    /// Moves a field to `position` in the field order, shifting the fields in between.
+    pub fn move_field(&mut self, field_name: &str, position: usize) -> Result<(), ModelsError> {
+        let index = self.fields.get_index_of(field_name).ok_or(ModelsError::FieldNotFound)?;
+        let position = position.min(self.fields.len() - 1);
+        if index != position {
+            self.push_history();
+            self.fields.move_index(index, position);
+            self.touch();
+        }
+        Ok(())
+    }
+
+    /// Attaches the file at `file_path`, attachment names must be unique within the record.
     pub fn add_file(&mut self, file_path: &Path) -> Result<(), ModelsError> {
-        let mut file = File::open(file_path)?;
-        let filename = match file_path.file_name() {
-            Some(name) => name.to_os_string(),
-            None => {
-                return Err(ModelsError::GetFilenameError);
-            }
-        };
-        let extension = match file_path.extension() {
-            Some(ext) => ext.to_os_string(),
-            None => {
-                return Err(ModelsError::GetFilenameError);
-            }
-        };
-        let mut buf = Vec::new();
-        let _ = file.read(buf.as_mut_slice())?;
-        let record_file = RecordFile {
-            filename,
-            extension,
-            content: buf,
-        };
+        let record_file = RecordFile::read(file_path)?;
+        if self.files().iter().any(|f| f.filename == record_file.filename) {
+            return Err(ModelsError::AttachmentAlreadyExists);
+        }
         match self.files.as_mut() {
-            Some(mut files) => files.push(record_file),
-            None => {
-                let mut files = Vec::new();
-                files.push(record_file);
-                self.files = Some(files);
-            }
+            Some(files) => files.push(record_file),
+            None => self.files = Some(vec![record_file]),
         }
+        self.touch();
         Ok(())
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Default for Record {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Folder {
+    pub(crate) id: Uuid,
     pub name: String,
     pub records: Vec<Record>,
-    pub subfolders: Option<Vec<Box<Folder>>>,
+    pub subfolders: Option<Vec<Folder>>,
+    pub(crate) created: DateTime<Utc>,
+    pub(crate) modified: DateTime<Utc>,
 }
 
 impl Folder {
     pub fn new(name: String) -> Self {
+        let now = Utc::now();
         Self {
+            id: Uuid::new_v4(),
             name,
             records: Vec::new(),
             subfolders: None,
+            created: now,
+            modified: now,
         }
     }
 
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn touch(&mut self) {
+        self.modified = Utc::now();
+    }
+
     pub fn rename(&mut self, new_name: String) {
         self.name = new_name;
+        self.touch();
     }
 
     pub fn add_record(&mut self, record: Record) {
         self.records.push(record);
+        self.touch();
     }
 
     pub fn add_folder(&mut self, folder: Self) {
-        let boxed = Box::new(folder);
         match self.subfolders.as_mut() {
-            Some(subfolders) => subfolders.push(boxed),
-            None => {
-                let mut subfolders = Vec::new();
-                subfolders.push(boxed);
-                self.subfolders = Some(subfolders);
-            }
+            Some(subfolders) => subfolders.push(folder),
+            None => self.subfolders = Some(vec![folder]),
+        }
+        self.touch();
+    }
+
+    /// Looks up a folder by id in the whole tree, including this folder itself.
+    pub fn find_folder(&self, id: Uuid) -> Option<&Folder> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.subfolders.iter().flatten().find_map(|f| f.find_folder(id))
+    }
+
+    pub fn find_folder_mut(&mut self, id: Uuid) -> Option<&mut Folder> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.subfolders.iter_mut().flatten().find_map(|f| f.find_folder_mut(id))
+    }
+
+    /// Looks up a record by id in this folder and all of its subfolders.
+    pub fn find_record(&self, id: Uuid) -> Option<&Record> {
+        match self.records.iter().find(|r| r.id == id) {
+            Some(record) => Some(record),
+            None => self.subfolders.iter().flatten().find_map(|f| f.find_record(id)),
+        }
+    }
+
+    pub fn find_record_mut(&mut self, id: Uuid) -> Option<&mut Record> {
+        match self.records.iter_mut().find(|r| r.id == id) {
+            Some(record) => Some(record),
+            None => self.subfolders.iter_mut().flatten().find_map(|f| f.find_record_mut(id)),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::models::{Folder, Record};
+    use crate::models::{Folder, ModelsError, Record, HISTORY_LIMIT};
+    use chrono::{Duration, Utc};
+    use indexmap::IndexMap;
     use std::path::Path;
+    use uuid::Uuid;
 
     fn create_record() -> Record {
         let mut record = Record::new();
         record
             .add_field("domain".into(), "yandex.ru".into())
             .unwrap();
-        let path = Path::new("../../tests/testfile.test");
-        record.add_file(&path).unwrap();
+        let path = Path::new("tests/testfile.test");
+        record.add_file(path).unwrap();
         record
     }
 
@@ -129,6 +334,14 @@ mod tests {
         create_record();
     }
 
+    #[test]
+    fn test_field_names_are_unique() {
+        let mut record = create_record();
+        assert!(matches!(record.add_field("domain".into(), "other".into()), Err(ModelsError::FieldAlreadyExist)));
+        assert!(matches!(record.add_field(String::new(), "value".into()), Err(ModelsError::EmptyFieldName)));
+        assert_eq!(record.fields["domain"].to_string(), "yandex.ru");
+    }
+
     #[test]
     fn test_create_folder() {
         let mut folder = Folder::new("test".into());
@@ -139,4 +352,135 @@ mod tests {
         subfolder.add_record(record);
         folder.add_folder(subfolder);
     }
+
+    #[test]
+    fn test_find_by_id() {
+        let mut folder = Folder::new("test".into());
+        let mut subfolder = Folder::new("sub".into());
+        let mut nested = Folder::new("nested".into());
+        let record = create_record();
+        let record_id = record.id();
+        let nested_id = nested.id();
+        nested.add_record(record);
+        subfolder.add_folder(nested);
+        folder.add_folder(subfolder);
+
+        assert_eq!(folder.find_folder(folder.id()).unwrap().name, "test");
+        assert_eq!(folder.find_folder(nested_id).unwrap().name, "nested");
+        assert_eq!(folder.find_record(record_id).unwrap().fields["domain"].to_string(), "yandex.ru");
+        folder.find_record_mut(record_id).unwrap().fields.clear();
+        assert!(folder.find_record(record_id).unwrap().fields.is_empty());
+        folder.find_folder_mut(nested_id).unwrap().rename("renamed".into());
+        assert_eq!(folder.find_folder(nested_id).unwrap().name, "renamed");
+        assert!(folder.find_record(Uuid::new_v4()).is_none());
+        assert!(folder.find_folder(Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let past = Utc::now() - Duration::days(1);
+        let mut record = Record::new();
+        assert_eq!(record.created(), record.modified());
+        record.modified = past;
+        record.add_field("login".into(), "admin".into()).unwrap();
+        assert!(record.modified() > past);
+        record.modified = past;
+        record.add_field("login".into(), "root".into()).unwrap_err();
+        assert_eq!(record.modified(), past);
+        record.set_fields(record.fields.clone());
+        assert_eq!(record.modified(), past);
+
+        let mut folder = Folder::new("test".into());
+        folder.modified = past;
+        folder.rename("renamed".into());
+        assert!(folder.modified() > past);
+        folder.modified = past;
+        folder.add_record(record);
+        assert!(folder.modified() > past);
+        folder.modified = past;
+        folder.add_folder(Folder::new("sub".into()));
+        assert!(folder.modified() > past);
+        assert!(folder.created() <= folder.modified());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut record = Record::new();
+        record.add_field("password".into(), "first".into()).unwrap();
+        let first_modified = record.modified();
+        let mut fields = record.fields.clone();
+        fields.insert("password".into(), "second".into());
+        record.set_fields(fields);
+
+        assert_eq!(record.history().len(), 1);
+        assert_eq!(record.history()[0].fields["password"].to_string(), "first");
+        assert_eq!(record.history()[0].modified, first_modified);
+
+        record.restore(0).unwrap();
+        assert_eq!(record.fields["password"].to_string(), "first");
+        assert_eq!(record.history().len(), 2);
+        assert_eq!(record.history()[1].fields["password"].to_string(), "second");
+        assert!(record.restore(2).is_err());
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut record = Record::new();
+        for i in 0..HISTORY_LIMIT + 5 {
+            let mut fields = IndexMap::new();
+            fields.insert("password".to_string(), i.to_string().into());
+            record.set_fields(fields);
+        }
+        assert_eq!(record.history().len(), HISTORY_LIMIT);
+        assert_eq!(record.history()[0].fields["password"].to_string(), "4");
+    }
+
+    #[test]
+    fn test_field_order() {
+        let mut record = Record::new();
+        for name in ["login", "password", "url"] {
+            record.add_field(name.into(), "value".into()).unwrap();
+        }
+        let names = |record: &Record| record.fields.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(names(&record), ["login", "password", "url"]);
+        assert!(record.add_field("password".into(), "again".into()).is_err());
+
+        record.move_field("url", 0).unwrap();
+        assert_eq!(names(&record), ["url", "login", "password"]);
+        let previous: Vec<_> = record.history().last().unwrap().fields.keys().cloned().collect();
+        assert_eq!(previous, ["login", "password", "url"]);
+        record.move_field("url", 10).unwrap();
+        assert_eq!(names(&record), ["login", "password", "url"]);
+        assert!(record.move_field("missing", 0).is_err());
+
+        let mut reordered = record.fields.clone();
+        reordered.move_index(0, 1);
+        record.set_fields(reordered);
+        assert_eq!(names(&record), ["password", "login", "url"]);
+
+        let bytes = bincode::serialize(&record).unwrap();
+        let decoded: Record = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(names(&decoded), ["password", "login", "url"]);
+    }
+
+    #[test]
+    fn test_tags_and_favorite() {
+        let mut record = Record::new();
+        record.add_tag(" prod ");
+        record.add_tag("");
+        record.add_tag("on-call");
+        assert_eq!(record.tags().iter().collect::<Vec<_>>(), ["on-call", "prod"]);
+        record.remove_tag("on-call");
+        assert_eq!(record.tags().len(), 1);
+        record.set_tags(["staging ".to_string(), " ".to_string()].into());
+        assert_eq!(record.tags().iter().collect::<Vec<_>>(), ["staging"]);
+
+        let past = Utc::now() - Duration::days(1);
+        record.modified = past;
+        record.set_favorite(false);
+        assert_eq!(record.modified(), past);
+        record.set_favorite(true);
+        assert!(record.is_favorite());
+        assert!(record.modified() > past);
+    }
 }
@@ -0,0 +1,265 @@
+use std::fmt;
+use std::str::FromStr;
+
+use uuid::Uuid;
+
+use crate::models::{Folder, ModelsError, Record};
+
+/// Slash-separated location of a folder or record, relative to the folder it is
+/// resolved against, e.g. `Infra/AWS/prod-root`. Slashes and backslashes inside
+/// names are escaped with a backslash: `CI\/CD` is the single name `CI/CD`.
+/// The empty path refers to the folder itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct VaultPath {
+    segments: Vec<String>,
+}
+
+/// Item found at a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathEntry<'a> {
+    Folder(&'a Folder),
+    Record(&'a Record),
+}
+
+impl VaultPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a path, ignoring leading and trailing slashes.
+    pub fn parse(path: &str) -> Result<Self, ModelsError> {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        let mut segments = Vec::new();
+        if path.is_empty() {
+            return Ok(Self { segments });
+        }
+        let mut segment = String::new();
+        let mut chars = path.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(escaped @ ('/' | '\\')) => segment.push(escaped),
+                    _ => return Err(ModelsError::InvalidPath(path.into())),
+                },
+                '/' => segments.push(std::mem::take(&mut segment)),
+                c => segment.push(c),
+            }
+        }
+        if !segment.is_empty() || !path.ends_with('/') {
+            segments.push(segment);
+        }
+        if segments.iter().any(String::is_empty) {
+            return Err(ModelsError::InvalidPath(path.into()));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Names of the folders and record along the path, unescaped.
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Path of the item named `name` inside this one.
+    pub fn join(&self, name: &str) -> Self {
+        let mut segments = self.segments.clone();
+        segments.push(name.to_string());
+        Self { segments }
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        let (_, parent) = self.segments.split_last()?;
+        Some(Self { segments: parent.to_vec() })
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.segments.last().map(String::as_str)
+    }
+}
+
+impl FromStr for VaultPath {
+    type Err = ModelsError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        Self::parse(path)
+    }
+}
+
+impl fmt::Display for VaultPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let segments: Vec<String> = self.segments.iter().map(|s| escape_name(s)).collect();
+        write!(f, "{}", segments.join("/"))
+    }
+}
+
+impl<'a> FromIterator<&'a str> for VaultPath {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(names: I) -> Self {
+        Self { segments: names.into_iter().map(String::from).collect() }
+    }
+}
+
+/// Escapes a folder or record name to be used as a path segment.
+pub fn escape_name(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('/', "\\/")
+}
+
+impl Record {
+    /// Name of the record in paths: its title, or the value of its first non-secret
+    /// field for records without one.
+    pub fn path_name(&self) -> Option<String> {
+        if !self.title.is_empty() {
+            return Some(self.title.clone());
+        }
+        self.fields
+            .values()
+            .find(|value| !value.is_secret())
+            .map(|value| value.to_string())
+    }
+}
+
+impl Folder {
+    /// Folder or record at `path`. When several items share a name, folders come
+    /// before records and earlier items before later ones.
+    pub fn resolve(&self, path: &VaultPath) -> Option<PathEntry<'_>> {
+        let Some((name, parents)) = path.segments.split_last() else {
+            return Some(PathEntry::Folder(self));
+        };
+        let parent = self.resolve_segments(parents)?;
+        if let Some(folder) = parent.subfolder_named(name) {
+            return Some(PathEntry::Folder(folder));
+        }
+        parent.records
+            .iter()
+            .find(|r| r.path_name().as_deref() == Some(name))
+            .map(PathEntry::Record)
+    }
+
+    pub fn resolve_folder(&self, path: &VaultPath) -> Option<&Folder> {
+        self.resolve_segments(&path.segments)
+    }
+
+    pub fn resolve_folder_mut(&mut self, path: &VaultPath) -> Option<&mut Folder> {
+        let mut folder = self;
+        for name in &path.segments {
+            folder = folder.subfolders.iter_mut().flatten().find(|f| &f.name == name)?;
+        }
+        Some(folder)
+    }
+
+    pub fn resolve_record(&self, path: &VaultPath) -> Option<&Record> {
+        match self.resolve(path)? {
+            PathEntry::Record(record) => Some(record),
+            PathEntry::Folder(_) => None,
+        }
+    }
+
+    /// Folder at `path`, creating it and any missing folder on the way, like `mkdir -p`.
+    pub fn create_folders(&mut self, path: &VaultPath) -> &mut Folder {
+        let mut folder = self;
+        for name in &path.segments {
+            let position = folder.subfolders.iter().flatten().position(|f| &f.name == name);
+            let index = match position {
+                Some(index) => index,
+                None => {
+                    folder.add_folder(Folder::new(name.clone()));
+                    folder.subfolders.as_ref().unwrap().len() - 1
+                }
+            };
+            folder = &mut folder.subfolders.as_mut().unwrap()[index];
+        }
+        folder
+    }
+
+    /// Subfolders then records of the folder at `path`.
+    pub fn list(&self, path: &VaultPath) -> Result<Vec<PathEntry<'_>>, ModelsError> {
+        let folder = self.resolve_folder(path).ok_or(ModelsError::FolderNotFound)?;
+        let folders = folder.subfolders.iter().flatten().map(PathEntry::Folder);
+        let records = folder.records.iter().map(PathEntry::Record);
+        Ok(folders.chain(records).collect())
+    }
+
+    /// Path of the folder or record with the given id, relative to this folder.
+    /// Records without a name cannot be addressed by path.
+    pub fn path_of(&self, id: Uuid) -> Option<VaultPath> {
+        if self.id == id {
+            return Some(VaultPath::new());
+        }
+        if let Some(record) = self.records.iter().find(|r| r.id == id) {
+            return Some(VaultPath { segments: vec![record.path_name()?] });
+        }
+        self.subfolders.iter().flatten().find_map(|subfolder| {
+            let mut path = subfolder.path_of(id)?;
+            path.segments.insert(0, subfolder.name.clone());
+            Some(path)
+        })
+    }
+
+    fn resolve_segments(&self, segments: &[String]) -> Option<&Folder> {
+        segments.iter().try_fold(self, |folder, name| folder.subfolder_named(name))
+    }
+
+    fn subfolder_named(&self, name: &str) -> Option<&Folder> {
+        self.subfolders.iter().flatten().find(|f| f.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{FieldKind, FieldValue, Folder, ModelsError, PathEntry, Record, VaultPath};
+
+    fn record(name: &str) -> Record {
+        let mut record = Record::new();
+        record.add_field("password".into(), FieldValue::new(FieldKind::Concealed, "hunter2").unwrap()).unwrap();
+        record.add_field("name".into(), name.into()).unwrap();
+        record
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let path = VaultPath::parse("/Infra/CI\\/CD/back\\\\slash/").unwrap();
+        assert_eq!(path.segments(), ["Infra", "CI/CD", "back\\slash"]);
+        assert_eq!(path.to_string(), "Infra/CI\\/CD/back\\\\slash");
+        assert_eq!(path.to_string().parse::<VaultPath>().unwrap(), path);
+        assert_eq!(path.parent().unwrap().name(), Some("CI/CD"));
+        assert!(VaultPath::parse("").unwrap().is_root());
+        assert!(VaultPath::parse("/").unwrap().is_root());
+        assert!(matches!(VaultPath::parse("a//b"), Err(ModelsError::InvalidPath(_))));
+        assert!(matches!(VaultPath::parse("a\\b"), Err(ModelsError::InvalidPath(_))));
+        assert!(matches!(VaultPath::parse("a\\"), Err(ModelsError::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_create_and_resolve() {
+        let mut root = Folder::new("main".into());
+        let aws = root.create_folders(&"Infra/AWS".parse().unwrap());
+        aws.add_record(record("prod-root"));
+        let aws_id = aws.id();
+        let ci = root.create_folders(&"Infra/CI\\/CD".parse().unwrap()).id();
+        assert_eq!(root.create_folders(&"Infra/AWS".parse().unwrap()).id(), aws_id);
+        assert_eq!(root.subfolders.as_ref().unwrap().len(), 1);
+
+        let path = "Infra/AWS/prod-root".parse().unwrap();
+        let record = root.resolve_record(&path).unwrap();
+        assert_eq!(root.path_of(record.id()), Some(path));
+        assert_eq!(root.resolve_folder(&"Infra/CI\\/CD".parse().unwrap()).unwrap().id(), ci);
+        assert_eq!(root.path_of(ci).unwrap().to_string(), "Infra/CI\\/CD");
+        assert!(root.resolve(&"Infra/GCP".parse().unwrap()).is_none());
+        assert!(matches!(root.resolve(&VaultPath::new()), Some(PathEntry::Folder(f)) if f.id() == root.id()));
+    }
+
+    #[test]
+    fn test_list() {
+        let mut root = Folder::new("main".into());
+        root.create_folders(&"Infra".parse().unwrap());
+        root.add_record(record("github"));
+        let names: Vec<String> = root.list(&VaultPath::new()).unwrap().iter().map(|entry| match entry {
+            PathEntry::Folder(folder) => folder.name.clone(),
+            PathEntry::Record(record) => record.path_name().unwrap(),
+        }).collect();
+        assert_eq!(names, ["Infra", "github"]);
+        assert!(matches!(root.list(&"missing".parse().unwrap()), Err(ModelsError::FolderNotFound)));
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeSet;
+
+use uuid::Uuid;
+
+use crate::models::{Folder, Record};
+
+/// Location of a record found by a search: ids of the folders leading to it,
+/// starting below the searched folder, and the id of the record itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordPath {
+    pub folders: Vec<Uuid>,
+    pub record: Uuid,
+}
+
+/// Restricts a search to records carrying all of `tags` and, optionally, to favorites.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordFilter {
+    pub tags: BTreeSet<String>,
+    pub favorites_only: bool,
+}
+
+impl RecordFilter {
+    fn accepts(&self, record: &Record) -> bool {
+        (!self.favorites_only || record.favorite) && self.tags.is_subset(&record.tags)
+    }
+}
+
+impl Record {
+    /// Case-insensitive match against the title and url, field names, non-secret values,
+    /// tags and attachment filenames. `query` is expected to be lowercase already.
+    fn matches(&self, query: &str) -> bool {
+        let title = self.title.to_lowercase().contains(query)
+            || self.url.iter().any(|url| url.to_lowercase().contains(query));
+        let fields = self.fields.iter().any(|(name, value)| {
+            name.to_lowercase().contains(query)
+                || (!value.is_secret() && value.to_string().to_lowercase().contains(query))
+        });
+        let tags = self.tags.iter().any(|tag| tag.to_lowercase().contains(query));
+        let files = self.files.iter().flatten().any(|file| {
+            file.filename.to_string_lossy().to_lowercase().contains(query)
+        });
+        title || fields || tags || files
+    }
+}
+
+impl Folder {
+    /// Finds records anywhere below this folder matching `query` case-insensitively.
+    /// Every record of a subfolder whose name matches is part of the result.
+    pub fn search(&self, query: &str) -> Vec<RecordPath> {
+        self.search_filtered(query, &RecordFilter::default())
+    }
+
+    /// Same as [`Folder::search`], keeping only records accepted by `filter`.
+    /// Results are ordered by the display name of the records.
+    pub fn search_filtered(&self, query: &str, filter: &RecordFilter) -> Vec<RecordPath> {
+        let query = query.to_lowercase();
+        let mut results = Vec::new();
+        self.search_records(&query, filter, &mut Vec::new(), false, &mut results);
+        results.sort_by(|(a, _), (b, _)| a.cmp(b));
+        results.into_iter().map(|(_, path)| path).collect()
+    }
+
+    /// Orders the records of this folder by display name, ignoring case.
+    pub fn sort_records(&mut self) {
+        self.records.sort_by_cached_key(|r| r.display_name().to_lowercase());
+        self.touch();
+    }
+
+    /// All tags used by records in this folder and its subfolders.
+    pub fn tags(&self) -> BTreeSet<String> {
+        let mut tags: BTreeSet<String> = self.records
+            .iter()
+            .flat_map(|r| r.tags.iter().cloned())
+            .collect();
+        for subfolder in self.subfolders.iter().flatten() {
+            tags.extend(subfolder.tags());
+        }
+        tags
+    }
+
+    fn search_records(
+        &self,
+        query: &str,
+        filter: &RecordFilter,
+        folders: &mut Vec<Uuid>,
+        folder_matches: bool,
+        results: &mut Vec<(String, RecordPath)>,
+    ) {
+        for record in &self.records {
+            if (folder_matches || record.matches(query)) && filter.accepts(record) {
+                results.push((record.display_name().to_lowercase(), RecordPath {
+                    folders: folders.clone(),
+                    record: record.id,
+                }));
+            }
+        }
+        for subfolder in self.subfolders.iter().flatten() {
+            let subfolder_matches = folder_matches || subfolder.name.to_lowercase().contains(query);
+            folders.push(subfolder.id);
+            subfolder.search_records(query, filter, folders, subfolder_matches, results);
+            folders.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::models::{FieldValue, Folder, Record};
+    use crate::models::search::{RecordFilter, RecordPath};
+
+    fn record(fields: Vec<(&str, FieldValue)>) -> Record {
+        let mut record = Record::new();
+        for (name, value) in fields {
+            record.add_field(name.into(), value).unwrap();
+        }
+        record
+    }
+
+    #[test]
+    fn test_search() {
+        let mut main = Folder::new("main".into());
+        let mut infra = Folder::new("Infra".into());
+        let mut aws = Folder::new("AWS".into());
+        let root = record(vec![
+            ("login", "root".into()),
+            ("password", FieldValue::Concealed("Sup3rSecret".into())),
+        ]);
+        let mut deploy = record(vec![("url", FieldValue::Url("https://ci.example.com".into()))]);
+        deploy.add_file(Path::new("tests/testfile.test")).unwrap();
+        let (root_id, deploy_id) = (root.id(), deploy.id());
+        let (infra_id, aws_id) = (infra.id(), aws.id());
+        aws.add_record(root);
+        infra.add_record(deploy);
+        infra.add_folder(aws);
+        main.add_folder(infra);
+
+        assert_eq!(main.search("LOGIN"), vec![RecordPath { folders: vec![infra_id, aws_id], record: root_id }]);
+        assert_eq!(main.search("example.COM"), vec![RecordPath { folders: vec![infra_id], record: deploy_id }]);
+        assert_eq!(main.search("testfile")[0].record, deploy_id);
+        assert_eq!(main.search("aws").len(), 1);
+        assert_eq!(main.search("infra").len(), 2);
+        assert!(main.search("sup3rsecret").is_empty());
+        assert!(main.search("main").is_empty());
+    }
+
+    #[test]
+    fn test_search_filtered() {
+        let mut main = Folder::new("main".into());
+        let mut prod = Folder::new("prod".into());
+        let mut db = record(vec![("login", "postgres".into())]);
+        db.add_tag("prod");
+        db.add_tag("on-call");
+        db.set_favorite(true);
+        let mut ci = record(vec![("login", "ci".into())]);
+        ci.add_tag("prod");
+        let (db_id, ci_id) = (db.id(), ci.id());
+        prod.add_record(db);
+        main.add_record(ci);
+        main.add_folder(prod);
+
+        let by_tag = RecordFilter { tags: ["prod".to_string()].into(), favorites_only: false };
+        assert_eq!(main.search_filtered("", &by_tag).len(), 2);
+        let by_tags = RecordFilter { tags: ["prod".to_string(), "on-call".to_string()].into(), favorites_only: false };
+        assert_eq!(main.search_filtered("", &by_tags)[0].record, db_id);
+        let favorites = RecordFilter { tags: Default::default(), favorites_only: true };
+        assert_eq!(main.search_filtered("login", &favorites)[0].record, db_id);
+        assert_eq!(main.search_filtered("ci", &by_tag)[0].record, ci_id);
+        assert_eq!(main.search("on-call")[0].record, db_id);
+        assert_eq!(main.tags().into_iter().collect::<Vec<_>>(), ["on-call", "prod"]);
+    }
+
+    #[test]
+    fn test_title_search_and_sort() {
+        let mut main = Folder::new("main".into());
+        let mut github = record(vec![("login", "octocat".into())]);
+        github.set_title("GitHub");
+        github.set_url(Some("https://github.com".into()));
+        let mut aws = record(vec![("login", "root".into())]);
+        aws.set_title("aws root");
+        let untitled = record(vec![("login", "Bob".into())]);
+        let (github_id, aws_id, untitled_id) = (github.id(), aws.id(), untitled.id());
+        main.add_record(github);
+        main.add_record(aws);
+        main.add_record(untitled);
+
+        assert_eq!(main.search("github.com")[0].record, github_id);
+        let ordered: Vec<_> = main.search("").into_iter().map(|path| path.record).collect();
+        assert_eq!(ordered, [aws_id, untitled_id, github_id]);
+
+        main.sort_records();
+        let names: Vec<_> = main.records.iter().map(|r| r.display_name()).collect();
+        assert_eq!(names, ["aws root", "Bob", "GitHub"]);
+    }
+}
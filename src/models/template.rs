@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::models::field::deserialize_fields;
+use crate::models::vault::record_removal;
+use crate::models::{FieldKind, FieldValue, ModelsError, Record, Vault};
+
+/// Shape of a kind of secret: the fields a new record starts with and the
+/// attachments it is expected to carry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordTemplate {
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_fields")]
+    pub fields: IndexMap<String, FieldKind>,
+    pub attachments: Vec<String>,
+    pub(crate) added: DateTime<Utc>,
+}
+
+impl RecordTemplate {
+    pub fn new(name: &str, fields: &[(&str, FieldKind)]) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: fields.iter().map(|(name, kind)| (name.to_string(), *kind)).collect(),
+            attachments: Vec::new(),
+            added: Utc::now(),
+        }
+    }
+
+    /// Templates every vault offers.
+    pub fn builtin() -> Vec<Self> {
+        let ssh_key = Self {
+            attachments: vec!["private key".to_string()],
+            ..Self::new("SSH key", &[
+                ("username", FieldKind::Text),
+                ("host", FieldKind::Text),
+                ("passphrase", FieldKind::Concealed),
+            ])
+        };
+        vec![
+            Self::new("Website login", &[
+                ("username", FieldKind::Text),
+                ("password", FieldKind::Concealed),
+                ("url", FieldKind::Url),
+                ("totp", FieldKind::Totp),
+            ]),
+            Self::new("Database", &[
+                ("host", FieldKind::Text),
+                ("port", FieldKind::Text),
+                ("user", FieldKind::Text),
+                ("password", FieldKind::Concealed),
+                ("dbname", FieldKind::Text),
+            ]),
+            ssh_key,
+            Self::new("API token", &[
+                ("token", FieldKind::Concealed),
+                ("url", FieldKind::Url),
+                ("expires", FieldKind::Date),
+            ]),
+        ]
+    }
+
+    /// Template with the field names and kinds of an existing record.
+    pub fn from_record(name: &str, record: &Record) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: record.fields.iter().map(|(name, value)| (name.clone(), value.kind())).collect(),
+            attachments: record.files().iter().map(|f| f.filename().to_string_lossy().to_string()).collect(),
+            added: Utc::now(),
+        }
+    }
+}
+
+impl FieldValue {
+    /// Value a new field of the given kind starts with: empty text, or today for dates.
+    pub fn empty(kind: FieldKind) -> Self {
+        match kind {
+            FieldKind::Date => FieldValue::Date(Utc::now().date_naive()),
+            kind => FieldValue::new(kind, "").unwrap(),
+        }
+    }
+}
+
+impl Record {
+    /// New record with the fields of `template`, all empty.
+    pub fn from_template(template: &RecordTemplate) -> Self {
+        let mut record = Record::new();
+        record.fields = template.fields
+            .iter()
+            .map(|(name, kind)| (name.clone(), FieldValue::empty(*kind)))
+            .collect();
+        record
+    }
+}
+
+impl Vault {
+    /// User-defined templates stored in the vault.
+    pub fn templates(&self) -> &[RecordTemplate] {
+        &self.templates
+    }
+
+    /// Built-in templates followed by the user-defined ones.
+    pub fn all_templates(&self) -> Vec<RecordTemplate> {
+        let mut templates = RecordTemplate::builtin();
+        templates.extend(self.templates.iter().cloned());
+        templates
+    }
+
+    pub fn find_template(&self, name: &str) -> Option<RecordTemplate> {
+        self.all_templates().into_iter().find(|t| t.name == name)
+    }
+
+    /// Stores a user-defined template, names must be unique among all templates.
+    pub fn add_template(&mut self, mut template: RecordTemplate) -> Result<(), ModelsError> {
+        if self.find_template(&template.name).is_some() {
+            return Err(ModelsError::TemplateAlreadyExists);
+        }
+        template.added = Utc::now();
+        self.templates.push(template);
+        self.root.touch();
+        Ok(())
+    }
+
+    pub fn remove_template(&mut self, name: &str) -> Result<RecordTemplate, ModelsError> {
+        let index = self.templates
+            .iter()
+            .position(|t| t.name == name)
+            .ok_or(ModelsError::TemplateNotFound)?;
+        record_removal(&mut self.removed_templates, name);
+        self.root.touch();
+        Ok(self.templates.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{FieldKind, FieldValue, Folder, ModelsError, Record, RecordTemplate, Vault};
+
+    #[test]
+    fn test_record_from_builtin_template() {
+        let vault = Vault::new(Folder::new("main".into()));
+        let template = vault.find_template("Database").unwrap();
+        let record = Record::from_template(&template);
+        let names: Vec<_> = record.fields.keys().map(String::as_str).collect();
+        assert_eq!(names, ["host", "port", "user", "password", "dbname"]);
+        assert_eq!(record.fields["password"], FieldValue::Concealed(String::new()));
+
+        let token = Record::from_template(&vault.find_template("API token").unwrap());
+        assert_eq!(token.fields["expires"].kind(), FieldKind::Date);
+        assert_eq!(vault.find_template("SSH key").unwrap().attachments, ["private key"]);
+    }
+
+    #[test]
+    fn test_user_defined_templates() {
+        let mut vault = Vault::new(Folder::new("main".into()));
+        let mut record = Record::new();
+        record.add_field("account".into(), "123".into()).unwrap();
+        record.add_field("secret key".into(), FieldValue::Concealed("s3cr3t".into())).unwrap();
+
+        vault.add_template(RecordTemplate::from_record("AWS account", &record)).unwrap();
+        assert!(matches!(
+            vault.add_template(RecordTemplate::new("Database", &[])),
+            Err(ModelsError::TemplateAlreadyExists)
+        ));
+        let created = Record::from_template(&vault.find_template("AWS account").unwrap());
+        assert_eq!(created.fields["secret key"], FieldValue::Concealed(String::new()));
+        assert_eq!(vault.all_templates().len(), RecordTemplate::builtin().len() + 1);
+
+        vault.remove_template("AWS account").unwrap();
+        assert!(vault.templates().is_empty());
+        assert!(matches!(vault.remove_template("Database"), Err(ModelsError::TemplateNotFound)));
+    }
+}
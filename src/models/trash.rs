@@ -0,0 +1,231 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::{Folder, ModelsError, Record, Vault};
+
+/// Days a deleted item is kept in the trash unless configured otherwise.
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TrashedItem {
+    Record(Record),
+    Folder(Folder),
+}
+
+impl TrashedItem {
+    pub fn id(&self) -> Uuid {
+        match self {
+            TrashedItem::Record(record) => record.id,
+            TrashedItem::Folder(folder) => folder.id,
+        }
+    }
+}
+
+/// Deleted record or folder along with where it was deleted from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrashEntry {
+    pub item: TrashedItem,
+    pub(crate) deleted: DateTime<Utc>,
+    pub(crate) parent: Uuid,
+    pub(crate) path: Vec<String>,
+}
+
+impl TrashEntry {
+    pub fn deleted(&self) -> DateTime<Utc> {
+        self.deleted
+    }
+
+    /// Names of the folders leading to the original location, from the vault root down.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+}
+
+/// Deleted items of a vault.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trash {
+    pub(crate) entries: Vec<TrashEntry>,
+    pub(crate) retention_days: u32,
+}
+
+impl Trash {
+    pub fn entries(&self) -> &[TrashEntry] {
+        &self.entries
+    }
+
+    pub fn retention_days(&self) -> u32 {
+        self.retention_days
+    }
+}
+
+impl Default for Trash {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+        }
+    }
+}
+
+impl Vault {
+    pub fn trash(&self) -> &Trash {
+        &self.trash
+    }
+
+    /// Moves a record from wherever it is in the tree to the trash.
+    pub fn trash_record(&mut self, id: Uuid) -> Result<(), ModelsError> {
+        let mut path = Vec::new();
+        let parent = parent_path(&self.root, &|f| f.records.iter().any(|r| r.id == id), &mut path)
+            .ok_or(ModelsError::RecordNotFound)?;
+        let record = self.root.remove_record(id)?;
+        self.push_trash(TrashedItem::Record(record), parent, path);
+        Ok(())
+    }
+
+    /// Moves a subfolder with everything in it to the trash.
+    pub fn trash_folder(&mut self, id: Uuid) -> Result<(), ModelsError> {
+        let mut path = Vec::new();
+        let is_parent = |f: &Folder| f.subfolders.iter().flatten().any(|s| s.id == id);
+        let parent = parent_path(&self.root, &is_parent, &mut path).ok_or(ModelsError::FolderNotFound)?;
+        let folder = self.root.remove_folder(id)?;
+        self.push_trash(TrashedItem::Folder(folder), parent, path);
+        Ok(())
+    }
+
+    /// Puts the trash entry at `index` back where it was deleted from, or into the root
+    /// folder if its original parent no longer exists. The restored item counts as
+    /// modified, so that merging with a copy that still has it in the trash keeps it.
+    pub fn restore_from_trash(&mut self, index: usize) -> Result<(), ModelsError> {
+        if index >= self.trash.entries.len() {
+            return Err(ModelsError::TrashEntryNotFound);
+        }
+        let entry = self.trash.entries.remove(index);
+        let parent = match self.root.find_folder_mut(entry.parent) {
+            Some(parent) => parent,
+            None => &mut self.root,
+        };
+        match entry.item {
+            TrashedItem::Record(mut record) => {
+                record.touch();
+                parent.add_record(record)
+            }
+            TrashedItem::Folder(mut folder) => {
+                folder.touch();
+                parent.add_folder(folder)
+            }
+        }
+        Ok(())
+    }
+
+    /// Permanently deletes the trash entry at `index`.
+    pub fn delete_from_trash(&mut self, index: usize) -> Result<TrashEntry, ModelsError> {
+        if index >= self.trash.entries.len() {
+            return Err(ModelsError::TrashEntryNotFound);
+        }
+        Ok(self.trash.entries.remove(index))
+    }
+
+    pub fn empty_trash(&mut self) {
+        self.trash.entries.clear();
+    }
+
+    pub fn set_trash_retention(&mut self, days: u32) {
+        self.trash.retention_days = days;
+    }
+
+    /// Permanently deletes entries that have been in the trash for longer than the
+    /// retention period and returns how many were deleted.
+    pub fn purge_trash(&mut self, now: DateTime<Utc>) -> usize {
+        let retention = Duration::days(self.trash.retention_days.into());
+        let before = self.trash.entries.len();
+        self.trash.entries.retain(|entry| now - entry.deleted < retention);
+        before - self.trash.entries.len()
+    }
+
+    fn push_trash(&mut self, item: TrashedItem, parent: Uuid, path: Vec<String>) {
+        self.trash.entries.push(TrashEntry {
+            item,
+            deleted: Utc::now(),
+            parent,
+            path,
+        });
+    }
+}
+
+/// Id of the first folder matching `is_parent`, with the names of the folders leading
+/// to it collected into `path`.
+fn parent_path(folder: &Folder, is_parent: &dyn Fn(&Folder) -> bool, path: &mut Vec<String>) -> Option<Uuid> {
+    if is_parent(folder) {
+        return Some(folder.id);
+    }
+    for subfolder in folder.subfolders.iter().flatten() {
+        path.push(subfolder.name.clone());
+        if let Some(id) = parent_path(subfolder, is_parent, path) {
+            return Some(id);
+        }
+        path.pop();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::models::{Folder, ModelsError, Record, TrashedItem, Vault};
+
+    fn tree() -> (Vault, uuid::Uuid, uuid::Uuid) {
+        let mut root = Folder::new("main".into());
+        let mut work = Folder::new("work".into());
+        let mut servers = Folder::new("servers".into());
+        let record = Record::new();
+        let record_id = record.id();
+        servers.add_record(record);
+        let servers_id = servers.id();
+        work.add_folder(servers);
+        root.add_folder(work);
+        (Vault::new(root), servers_id, record_id)
+    }
+
+    #[test]
+    fn test_trash_and_restore_record() {
+        let (mut vault, servers_id, record_id) = tree();
+        vault.trash_record(record_id).unwrap();
+        assert!(vault.root.find_record(record_id).is_none());
+        let entry = &vault.trash().entries()[0];
+        assert_eq!(entry.path(), ["work", "servers"]);
+        assert!(matches!(&entry.item, TrashedItem::Record(r) if r.id() == record_id));
+
+        vault.restore_from_trash(0).unwrap();
+        assert!(vault.trash().entries().is_empty());
+        assert_eq!(vault.root.find_folder(servers_id).unwrap().records[0].id(), record_id);
+        assert!(matches!(vault.restore_from_trash(0), Err(ModelsError::TrashEntryNotFound)));
+    }
+
+    #[test]
+    fn test_restore_into_root_when_parent_is_gone() {
+        let (mut vault, servers_id, record_id) = tree();
+        vault.trash_record(record_id).unwrap();
+        vault.trash_folder(servers_id).unwrap();
+        assert_eq!(vault.trash().entries()[1].path(), ["work"]);
+
+        vault.delete_from_trash(1).unwrap();
+        vault.restore_from_trash(0).unwrap();
+        assert_eq!(vault.root.records[0].id(), record_id);
+    }
+
+    #[test]
+    fn test_purge_trash() {
+        let (mut vault, servers_id, record_id) = tree();
+        vault.trash_record(record_id).unwrap();
+        vault.trash_folder(servers_id).unwrap();
+        vault.set_trash_retention(7);
+        assert_eq!(vault.purge_trash(Utc::now() + Duration::days(6)), 0);
+        assert_eq!(vault.purge_trash(Utc::now() + Duration::days(8)), 2);
+
+        vault.trash_folder(vault.root.subfolders.as_ref().unwrap()[0].id()).unwrap();
+        vault.empty_trash();
+        assert!(vault.trash().entries().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::models::{Folder, ModelsError, Record};
+
+impl Record {
+    /// Copy of the record under a new id, with fresh timestamps and no history.
+    pub fn duplicate(&self) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            created: now,
+            modified: now,
+            history: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+impl Folder {
+    /// Removes a record from wherever it is in the tree and returns it.
+    pub fn remove_record(&mut self, id: Uuid) -> Result<Record, ModelsError> {
+        let parent = self.record_parent_mut(id).ok_or(ModelsError::RecordNotFound)?;
+        let index = parent.records.iter().position(|r| r.id == id).unwrap();
+        let record = parent.records.remove(index);
+        parent.touch();
+        Ok(record)
+    }
+
+    /// Removes a subfolder, with everything in it, from wherever it is in the tree.
+    /// The folder this is called on cannot be removed.
+    pub fn remove_folder(&mut self, id: Uuid) -> Result<Folder, ModelsError> {
+        let parent = self.folder_parent_mut(id).ok_or(ModelsError::FolderNotFound)?;
+        let subfolders = parent.subfolders.as_mut().unwrap();
+        let index = subfolders.iter().position(|f| f.id == id).unwrap();
+        let folder = subfolders.remove(index);
+        if subfolders.is_empty() {
+            parent.subfolders = None;
+        }
+        parent.touch();
+        Ok(folder)
+    }
+
+    /// Moves a record into the folder `target`. The record counts as modified, so
+    /// that merging with another copy of the vault keeps the new location.
+    pub fn move_record(&mut self, id: Uuid, target: Uuid) -> Result<(), ModelsError> {
+        if self.find_folder(target).is_none() {
+            return Err(ModelsError::FolderNotFound);
+        }
+        let mut record = self.remove_record(id)?;
+        record.touch();
+        self.find_folder_mut(target).unwrap().add_record(record);
+        Ok(())
+    }
+
+    /// Moves a subfolder into the folder `target`, which must not be the moved
+    /// folder itself or one of its descendants. Like a moved record, the folder counts
+    /// as modified.
+    pub fn move_folder(&mut self, id: Uuid, target: Uuid) -> Result<(), ModelsError> {
+        if id == self.id {
+            return Err(ModelsError::InvalidMove);
+        }
+        let folder = self.find_folder(id).ok_or(ModelsError::FolderNotFound)?;
+        if folder.find_folder(target).is_some() {
+            return Err(ModelsError::InvalidMove);
+        }
+        if self.find_folder(target).is_none() {
+            return Err(ModelsError::FolderNotFound);
+        }
+        let mut folder = self.remove_folder(id)?;
+        folder.touch();
+        self.find_folder_mut(target).unwrap().add_folder(folder);
+        Ok(())
+    }
+
+    /// Duplicates a record next to the original and returns the id of the copy.
+    pub fn copy_record(&mut self, id: Uuid) -> Result<Uuid, ModelsError> {
+        let parent = self.record_parent_mut(id).ok_or(ModelsError::RecordNotFound)?;
+        let index = parent.records.iter().position(|r| r.id == id).unwrap();
+        let copy = parent.records[index].duplicate();
+        let copy_id = copy.id;
+        parent.records.insert(index + 1, copy);
+        parent.touch();
+        Ok(copy_id)
+    }
+
+    fn record_parent_mut(&mut self, id: Uuid) -> Option<&mut Folder> {
+        if self.records.iter().any(|r| r.id == id) {
+            return Some(self);
+        }
+        self.subfolders.iter_mut().flatten().find_map(|f| f.record_parent_mut(id))
+    }
+
+    fn folder_parent_mut(&mut self, id: Uuid) -> Option<&mut Folder> {
+        if self.subfolders.iter().flatten().any(|f| f.id == id) {
+            return Some(self);
+        }
+        self.subfolders.iter_mut().flatten().find_map(|f| f.folder_parent_mut(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::models::{Folder, ModelsError, Record};
+
+    fn tree() -> (Folder, Uuid, Uuid, Uuid) {
+        let mut root = Folder::new("main".into());
+        let mut work = Folder::new("work".into());
+        let servers = Folder::new("servers".into());
+        let servers_id = servers.id();
+        work.add_folder(servers);
+        let work_id = work.id();
+        let mut record = Record::new();
+        record.add_field("login".into(), "admin".into()).unwrap();
+        let record_id = record.id();
+        root.add_record(record);
+        root.add_folder(work);
+        (root, work_id, servers_id, record_id)
+    }
+
+    #[test]
+    fn test_move_record() {
+        let (mut root, _, servers_id, record_id) = tree();
+        let modified = root.find_record(record_id).unwrap().modified();
+        root.move_record(record_id, servers_id).unwrap();
+        assert!(root.records.is_empty());
+        assert_eq!(root.find_folder(servers_id).unwrap().records[0].id(), record_id);
+        assert!(root.find_record(record_id).unwrap().modified() > modified);
+        assert!(matches!(
+            root.move_record(record_id, Uuid::new_v4()),
+            Err(ModelsError::FolderNotFound)
+        ));
+        assert!(root.find_record(record_id).is_some());
+        assert!(matches!(
+            root.move_record(Uuid::new_v4(), servers_id),
+            Err(ModelsError::RecordNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_move_folder() {
+        let (mut root, work_id, servers_id, _) = tree();
+        assert!(matches!(root.move_folder(work_id, servers_id), Err(ModelsError::InvalidMove)));
+        assert!(matches!(root.move_folder(work_id, work_id), Err(ModelsError::InvalidMove)));
+        assert!(matches!(root.move_folder(root.id(), work_id), Err(ModelsError::InvalidMove)));
+
+        root.move_folder(servers_id, root.id()).unwrap();
+        let names: Vec<_> = root.subfolders.as_ref().unwrap().iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["work", "servers"]);
+        assert!(root.find_folder(work_id).unwrap().subfolders.is_none());
+    }
+
+    #[test]
+    fn test_copy_record() {
+        let (mut root, _, _, record_id) = tree();
+        let copy_id = root.copy_record(record_id).unwrap();
+        assert_ne!(copy_id, record_id);
+        assert_eq!(root.records[1].id(), copy_id);
+        assert_eq!(root.records[1].fields, root.records[0].fields);
+        assert!(root.records[1].history().is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let (mut root, work_id, servers_id, record_id) = tree();
+        assert_eq!(root.remove_record(record_id).unwrap().id(), record_id);
+        assert!(root.find_record(record_id).is_none());
+        assert_eq!(root.remove_folder(work_id).unwrap().name, "work");
+        assert!(root.find_folder(servers_id).is_none());
+        assert!(root.subfolders.is_none());
+        assert!(matches!(root.remove_folder(root.id()), Err(ModelsError::FolderNotFound)));
+    }
+}
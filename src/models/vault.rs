@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Folder, RecordTemplate, Trash, VaultMember};
+
+/// Everything stored in a vault file: the folder tree, and the trash, the user-defined
+/// templates and the members, which belong to the vault as a whole rather than to a folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vault {
+    pub root: Folder,
+    pub(crate) trash: Trash,
+    pub(crate) templates: Vec<RecordTemplate>,
+    pub(crate) members: Vec<VaultMember>,
+    pub(crate) removed_templates: Vec<Removed>,
+    pub(crate) removed_members: Vec<Removed>,
+}
+
+/// Template or member that was removed, kept so that merging with a copy of the vault
+/// that still has it does not bring it back. Templates are identified by their name
+/// and members by their public key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Removed {
+    pub key: String,
+    pub(crate) removed: DateTime<Utc>,
+}
+
+impl Vault {
+    /// Vault with `root` as its tree, an empty trash and no templates or members.
+    pub fn new(root: Folder) -> Self {
+        Self {
+            root,
+            trash: Trash::default(),
+            templates: Vec::new(),
+            members: Vec::new(),
+            removed_templates: Vec::new(),
+            removed_members: Vec::new(),
+        }
+    }
+}
+
+/// Records that the item with `key` was removed just now.
+pub(crate) fn record_removal(removals: &mut Vec<Removed>, key: &str) {
+    removals.retain(|r| r.key != key);
+    removals.push(Removed {
+        key: key.to_string(),
+        removed: Utc::now(),
+    });
+}
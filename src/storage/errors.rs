@@ -0,0 +1,13 @@
+use crate::encryption::EncryptionError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Error accessing the vault file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+
+    #[error("Home directory not found")]
+    HomeDirNotFound,
+}
@@ -1,11 +1,11 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::path::PathBuf;
-use std::fs;
 
-use crate::models::Folder;
-use crate::storage::StorageManager;
-use crate::encryption::{AgeEncryptor, Encryptor};
-
-const EXTENSION: &'static str = "conf";
+use crate::models::Vault;
+use crate::storage::{StorageError, StorageManager};
+use crate::encryption::{recipient_count, AgeEncryptor, Encryptor};
 
 pub struct LocalStorageManager {
     pub(crate) path: PathBuf,
@@ -19,16 +19,97 @@ impl LocalStorageManager {
             encryptor,
         }
     }
+
+    /// Number of public keys the vault file is encrypted to, zero if there is no vault
+    /// file yet or it is protected by a passphrase.
+    pub fn recipient_count(&self) -> Result<usize, StorageError> {
+        match fs::read(&self.path) {
+            Ok(encrypted) => Ok(recipient_count(&encrypted)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(error) => Err(error.into()),
+        }
+    }
 }
 
 impl StorageManager for LocalStorageManager {
-    fn save(&self, data: &mut Folder) {
-        let encrypted_data = self.encryptor.encrypt(data);
-        fs::write(&self.path, encrypted_data).unwrap();
+    /// Writes the vault to a temporary file next to it and renames that over the vault,
+    /// so that a failed or interrupted save leaves the previous vault intact.
+    fn save(&self, data: &mut Vault) -> Result<(), StorageError> {
+        let encrypted_data = self.encryptor.encrypt(data)?;
+        let mut temp_name = self.path.file_name().map(OsString::from).unwrap_or_default();
+        temp_name.push(".tmp");
+        let temp_path = self.path.with_file_name(temp_name);
+        let result = File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(&encrypted_data)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp_path, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        Ok(result?)
+    }
+
+    fn load(&self) -> Result<Vault, StorageError> {
+        let encrypted_data = fs::read(&self.path)?;
+        Ok(self.encryptor.decrypt(encrypted_data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use age::x25519;
+
+    use crate::encryption::{AgeEncryptor, EncryptionError};
+    use crate::models::{Folder, Vault};
+    use crate::storage::{LocalStorageManager, StorageError, StorageManager};
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nordstone.cfg");
+        let storage = LocalStorageManager::new(path.clone(), AgeEncryptor::new("key".into()));
+        assert_eq!(storage.recipient_count().unwrap(), 0);
+        let mut vault = Vault::new(Folder::new("main".into()));
+        storage.save(&mut vault).unwrap();
+        assert_eq!(storage.load().unwrap(), vault);
+        assert_eq!(storage.recipient_count().unwrap(), 0);
+
+        let wrong_key = LocalStorageManager::new(path.clone(), AgeEncryptor::new("other".into()));
+        assert!(matches!(wrong_key.load(), Err(StorageError::Encryption(EncryptionError::WrongKey))));
+
+        let identity = x25519::Identity::generate();
+        let keyed = LocalStorageManager::new(path, AgeEncryptor::with_identities(vec![identity], Vec::new()));
+        keyed.save(&mut vault).unwrap();
+        assert_eq!(keyed.recipient_count().unwrap(), 1);
     }
 
-    fn load(&self) -> Folder {
-        let encrypted_data = fs::read(&self.path).unwrap();
-        self.encryptor.decrypt(encrypted_data)
+    #[test]
+    fn test_io_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = LocalStorageManager::new(dir.path().join("missing.cfg"), AgeEncryptor::new("key".into()));
+        assert!(matches!(missing.load(), Err(StorageError::Io(_))));
+
+        let not_a_file = dir.path().join("directory");
+        fs::create_dir(&not_a_file).unwrap();
+        let storage = LocalStorageManager::new(not_a_file, AgeEncryptor::new("key".into()));
+        assert!(matches!(storage.save(&mut Vault::new(Folder::new("main".into()))), Err(StorageError::Io(_))));
+        assert!(!dir.path().join("directory.tmp").exists());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_save_replaces_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nordstone.cfg");
+        let storage = LocalStorageManager::new(path.clone(), AgeEncryptor::new("key".into()));
+        storage.save(&mut Vault::new(Folder::new("old".into()))).unwrap();
+        storage.save(&mut Vault::new(Folder::new("new".into()))).unwrap();
+
+        assert_eq!(storage.load().unwrap().root.name, "new");
+        let files: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(files, vec![path]);
+    }
+}
@@ -1,10 +1,12 @@
+mod errors;
 mod local;
 
-use crate::models::Folder;
+use crate::models::Vault;
 
+pub use errors::StorageError;
 pub use local::LocalStorageManager;
 
 pub trait StorageManager {
-    fn save(&self, data: &mut Folder);
-    fn load(&self) -> Folder;
-}
\ No newline at end of file
+    fn save(&self, data: &mut Vault) -> Result<(), StorageError>;
+    fn load(&self) -> Result<Vault, StorageError>;
+}
@@ -0,0 +1,19 @@
+use crate::encryption::EncryptionError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("Telegram request failed")]
+    RequestError(#[from] teloxide::RequestError),
+
+    #[error("Error downloading vault document")]
+    DownloadError(#[from] teloxide::DownloadError),
+
+    #[error("Uploaded message has no document")]
+    MissingDocument,
+
+    #[error("No vault document found in chat")]
+    VaultNotFound,
+
+    #[error("Error encrypting or decrypting the vault")]
+    Encryption(#[from] EncryptionError),
+}
@@ -0,0 +1,710 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use uuid::Uuid;
+
+use crate::models::{
+    FieldValue, Folder, Record, RecordTemplate, RecordVersion, Removed, Trash, TrashedItem, Vault,
+    VaultMember,
+};
+
+/// Edit that differs between the local and the remote vault. The merged vault
+/// keeps the side of the more recently modified record, both values are reported here.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeConflict {
+    Field {
+        path: Vec<String>,
+        record: Uuid,
+        field: String,
+        local: FieldValue,
+        remote: FieldValue,
+    },
+    File {
+        path: Vec<String>,
+        record: Uuid,
+        filename: OsString,
+    },
+}
+
+#[derive(Debug)]
+pub struct MergeResult {
+    pub vault: Vault,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Merges two versions of a vault.
+///
+/// Folders and records are matched by id anywhere in the tree, a moved item ends up
+/// in the location of the side that modified it last. Anything present on only one
+/// side is kept, and matching records get the union of their fields and files. A field
+/// edited on one side only takes the edited value; fields edited on both sides and
+/// differing files are reported as conflicts and resolved in favour of the last writer.
+///
+/// The trash records deletions: both trashes are merged, and items in the trash of
+/// either side are removed from the tree unless they were modified or restored after
+/// they were deleted.
+///
+/// Templates are matched by name and members by public key. One removed on either
+/// side stays removed, unless it was added again after the removal.
+pub fn merge(remote_data: Vault, local_vault: Vault) -> MergeResult {
+    let mut conflicts = Vec::new();
+    let root = merge_trees(remote_data.root, local_vault.root, &mut conflicts);
+    let trash = merge_trash(remote_data.trash, local_vault.trash);
+    let (templates, removed_templates) = merge_listed(
+        remote_data.templates,
+        local_vault.templates,
+        remote_data.removed_templates,
+        local_vault.removed_templates,
+    );
+    let (members, removed_members) = merge_listed(
+        remote_data.members,
+        local_vault.members,
+        remote_data.removed_members,
+        local_vault.removed_members,
+    );
+    let mut vault = Vault {
+        root,
+        trash,
+        templates,
+        members,
+        removed_templates,
+        removed_members,
+    };
+    apply_deletions(&mut vault);
+    MergeResult { vault, conflicts }
+}
+
+/// Template or member of a vault.
+trait Listed {
+    /// Name of a template, public key of a member.
+    fn key(&self) -> &str;
+    fn added(&self) -> DateTime<Utc>;
+}
+
+impl Listed for RecordTemplate {
+    fn key(&self) -> &str {
+        &self.name
+    }
+
+    fn added(&self) -> DateTime<Utc> {
+        self.added
+    }
+}
+
+impl Listed for VaultMember {
+    fn key(&self) -> &str {
+        &self.public_key
+    }
+
+    fn added(&self) -> DateTime<Utc> {
+        self.added
+    }
+}
+
+/// Items of both sides, of two with the same key the one added last, except those
+/// removed on either side after they were added. Returns them along with the removals
+/// of both sides.
+fn merge_listed<T: Listed>(
+    remote: Vec<T>,
+    mut local: Vec<T>,
+    remote_removed: Vec<Removed>,
+    mut removed: Vec<Removed>,
+) -> (Vec<T>, Vec<Removed>) {
+    for removal in remote_removed {
+        match removed.iter_mut().find(|r| r.key == removal.key) {
+            Some(local_removal) => local_removal.removed = local_removal.removed.max(removal.removed),
+            None => removed.push(removal),
+        }
+    }
+    for item in remote {
+        match local.iter_mut().find(|i| i.key() == item.key()) {
+            Some(local_item) if item.added() > local_item.added() => *local_item = item,
+            Some(_) => {}
+            None => local.push(item),
+        }
+    }
+    local.retain(|item| removed.iter().all(|r| r.key != item.key() || r.removed < item.added()));
+    (local, removed)
+}
+
+/// Entries of both trashes, each deleted item once. The local retention is kept.
+fn merge_trash(remote: Trash, mut local: Trash) -> Trash {
+    for entry in remote.entries {
+        if local.entries.iter().all(|e| e.item.id() != entry.item.id()) {
+            local.entries.push(entry);
+        }
+    }
+    local
+}
+
+/// Moves items that are in the trash out of the tree, taking the version from the
+/// tree so that edits made before the deletion are kept. Items modified after they
+/// were deleted stay in the tree and their trash entries are dropped.
+fn apply_deletions(vault: &mut Vault) {
+    for mut entry in std::mem::take(&mut vault.trash.entries) {
+        let id = entry.item.id();
+        let modified = match entry.item {
+            TrashedItem::Record(_) => vault.root.find_record(id).map(Record::modified),
+            TrashedItem::Folder(_) => vault.root.find_folder(id).map(Folder::modified),
+        };
+        match modified {
+            Some(modified) if modified > entry.deleted => continue,
+            Some(_) => {
+                entry.item = match entry.item {
+                    TrashedItem::Record(_) => TrashedItem::Record(vault.root.remove_record(id).unwrap()),
+                    TrashedItem::Folder(_) => TrashedItem::Folder(vault.root.remove_folder(id).unwrap()),
+                };
+            }
+            None => {}
+        }
+        vault.trash.entries.push(entry);
+    }
+}
+
+/// Records and folders of a tree by id, each with the id of the folder it is in.
+/// The folders are stored without their records and subfolders.
+#[derive(Default)]
+struct FlatTree {
+    folders: IndexMap<Uuid, (Option<Uuid>, Folder)>,
+    records: IndexMap<Uuid, (Uuid, Record)>,
+}
+
+impl FlatTree {
+    fn new(root: Folder) -> Self {
+        let mut tree = Self::default();
+        tree.insert(root, None);
+        tree
+    }
+
+    fn insert(&mut self, mut folder: Folder, parent: Option<Uuid>) {
+        for record in std::mem::take(&mut folder.records) {
+            self.records.insert(record.id, (folder.id, record));
+        }
+        let subfolders = folder.subfolders.take().unwrap_or_default();
+        let id = folder.id;
+        self.folders.insert(id, (parent, folder));
+        for subfolder in subfolders {
+            self.insert(subfolder, Some(id));
+        }
+    }
+
+    /// Whether following the parents of `id` leads to `root` rather than into a cycle.
+    fn reaches(&self, mut id: Uuid, root: Uuid) -> bool {
+        for _ in 0..self.folders.len() {
+            if id == root {
+                return true;
+            }
+            match self.folders[&id].0 {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Names of the folders from below `root` down to `id`.
+    fn path(&self, mut id: Uuid, root: Uuid) -> Vec<String> {
+        let mut path = Vec::new();
+        while id != root {
+            let (parent, folder) = &self.folders[&id];
+            path.push(folder.name.clone());
+            id = parent.unwrap();
+        }
+        path.reverse();
+        path
+    }
+
+    /// Puts the tree under `root` back together, in the order the items were inserted.
+    fn assemble(self, root: Uuid) -> Folder {
+        let mut subfolders: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        let mut folders = HashMap::new();
+        for (id, (parent, folder)) in self.folders {
+            if let Some(parent) = parent {
+                subfolders.entry(parent).or_default().push(id);
+            }
+            folders.insert(id, folder);
+        }
+        let mut records: HashMap<Uuid, Vec<Record>> = HashMap::new();
+        for (parent, record) in self.records.into_values() {
+            records.entry(parent).or_default().push(record);
+        }
+        build(root, &mut folders, &subfolders, &mut records)
+    }
+}
+
+fn build(
+    id: Uuid,
+    folders: &mut HashMap<Uuid, Folder>,
+    subfolders: &HashMap<Uuid, Vec<Uuid>>,
+    records: &mut HashMap<Uuid, Vec<Record>>,
+) -> Folder {
+    let mut folder = folders.remove(&id).unwrap();
+    folder.records = records.remove(&id).unwrap_or_default();
+    let children: Vec<Folder> = subfolders
+        .get(&id)
+        .into_iter()
+        .flatten()
+        .map(|&child| build(child, folders, subfolders, records))
+        .collect();
+    folder.subfolders = (!children.is_empty()).then_some(children);
+    folder
+}
+
+/// Merges two trees, matching folders and records by id wherever they are. An item
+/// that was moved ends up where the side that modified it last put it, and the
+/// newer name of a folder wins. The roots are merged with each other whatever their ids.
+fn merge_trees(remote: Folder, local: Folder, conflicts: &mut Vec<MergeConflict>) -> Folder {
+    let root = local.id;
+    let remote_root = remote.id;
+    let in_local = |id: Uuid| if id == remote_root { root } else { id };
+    let mut tree = FlatTree::new(local);
+    let remote = FlatTree::new(remote);
+
+    for (id, (parent, remote_folder)) in remote.folders {
+        let id = in_local(id);
+        let parent = parent.map(in_local);
+        match tree.folders.get_mut(&id) {
+            Some((local_parent, local_folder)) => {
+                if remote_folder.modified > local_folder.modified {
+                    if id != root {
+                        *local_parent = parent;
+                    }
+                    local_folder.name = remote_folder.name;
+                    local_folder.modified = remote_folder.modified;
+                }
+            }
+            None => {
+                tree.folders.insert(id, (parent, remote_folder));
+            }
+        }
+    }
+    // Folders moved into each other on different sides would form a cycle, the first
+    // one found is put back in the root.
+    let ids: Vec<Uuid> = tree.folders.keys().copied().collect();
+    for id in ids {
+        if !tree.reaches(id, root) {
+            tree.folders[&id].0 = Some(root);
+        }
+    }
+
+    for (id, (parent, remote_record)) in remote.records {
+        let parent = in_local(parent);
+        match tree.records.get(&id).map(|(local_parent, local_record)| (*local_parent, local_record.modified)) {
+            Some((local_parent, modified)) => {
+                let parent = if remote_record.modified > modified { parent } else { local_parent };
+                let path = tree.path(parent, root);
+                let (local_parent, local_record) = &mut tree.records[&id];
+                *local_parent = parent;
+                merge_records(remote_record, local_record, &path, conflicts);
+            }
+            None => {
+                tree.records.insert(id, (parent, remote_record));
+            }
+        }
+    }
+    tree.assemble(root)
+}
+
+/// Whether `record` had `value` in `field` in one of its previous versions.
+fn had_value(record: &Record, field: &str, value: &FieldValue) -> bool {
+    record.history.iter().any(|version| version.fields.get(field) == Some(value))
+}
+
+/// Merges `remote` into `local`. A field that differs is taken from the side that
+/// edited it, recognized by the other side's value being in its history. When both
+/// sides edited it, that is a conflict, resolved in favour of the last writer. The
+/// values that are replaced or lose a conflict are kept in the history.
+fn merge_records(
+    remote: Record,
+    local: &mut Record,
+    path: &[String],
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    let remote_is_newer = remote.modified > local.modified;
+    let mut fields = local.fields.clone();
+    let mut replaced = false;
+    let mut remote_lost = false;
+    for (field, remote_value) in &remote.fields {
+        match local.fields.get(field) {
+            Some(local_value) if local_value != remote_value => {
+                let local_is_old = had_value(&remote, field, local_value);
+                let remote_is_old = had_value(local, field, remote_value);
+                let take_remote = if local_is_old != remote_is_old {
+                    local_is_old
+                } else {
+                    conflicts.push(MergeConflict::Field {
+                        path: path.to_vec(),
+                        record: local.id,
+                        field: field.clone(),
+                        local: local_value.clone(),
+                        remote: remote_value.clone(),
+                    });
+                    remote_lost |= !remote_is_newer;
+                    remote_is_newer
+                };
+                if take_remote {
+                    fields.insert(field.clone(), remote_value.clone());
+                    replaced = true;
+                }
+            }
+            Some(_) => {}
+            None => {
+                fields.insert(field.clone(), remote_value.clone());
+            }
+        }
+    }
+    if remote_lost {
+        local.push_version(RecordVersion {
+            fields: remote.fields.clone(),
+            modified: remote.modified,
+        });
+    }
+    if replaced {
+        local.push_history();
+    }
+    local.fields = fields;
+
+    for remote_file in remote.files.unwrap_or_default() {
+        let local_files = local.files.get_or_insert_with(Vec::new);
+        match local_files.iter_mut().find(|f| f.filename == remote_file.filename) {
+            Some(local_file) if local_file.content != remote_file.content => {
+                conflicts.push(MergeConflict::File {
+                    path: path.to_vec(),
+                    record: local.id,
+                    filename: remote_file.filename.clone(),
+                });
+                if remote_is_newer {
+                    *local_file = remote_file;
+                }
+            }
+            Some(_) => {}
+            None => local_files.push(remote_file),
+        }
+    }
+    local.tags.extend(remote.tags);
+    if remote_is_newer {
+        local.favorite = remote.favorite;
+        local.title = remote.title;
+        local.icon = remote.icon;
+        local.url = remote.url;
+    }
+    local.modified = local.modified.max(remote.modified);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::models::{FieldKind, Folder, Record, RecordTemplate, TrashedItem, Vault};
+    use crate::sync::merge::{self, MergeConflict, MergeResult};
+
+    fn record(fields: &[(&str, &str)]) -> Record {
+        let mut record = Record::new();
+        for (name, value) in fields {
+            record.add_field(name.to_string(), (*value).into()).unwrap();
+        }
+        record
+    }
+
+    fn subfolder<'a>(folder: &'a Folder, name: &str) -> &'a Folder {
+        folder.subfolders.as_ref().unwrap().iter().find(|f| f.name == name).unwrap()
+    }
+
+    /// main/infra/aws with a single record in aws.
+    fn create_tree() -> Folder {
+        let mut main = Folder::new("main".into());
+        let mut infra = Folder::new("infra".into());
+        let mut aws = Folder::new("aws".into());
+        aws.add_record(record(&[("login", "root"), ("password", "initial")]));
+        infra.add_folder(aws);
+        main.add_folder(infra);
+        main
+    }
+
+    fn merge(remote: Folder, local: Folder) -> MergeResult {
+        merge::merge(Vault::new(remote), Vault::new(local))
+    }
+
+    fn aws_mut(folder: &mut Folder) -> &mut Folder {
+        let infra = &mut folder.subfolders.as_mut().unwrap()[0];
+        &mut infra.subfolders.as_mut().unwrap()[0]
+    }
+
+    #[test]
+    fn test_merge_identical() {
+        let folder = create_tree();
+        let result = merge(folder.clone(), folder.clone());
+        assert_eq!(result.vault.root, folder);
+        assert!(result.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_merge_unions_nested_additions() {
+        let mut local = create_tree();
+        local.add_folder(Folder::new("local only".into()));
+        let mut remote = local.clone();
+        remote.subfolders.as_mut().unwrap().pop();
+        let remote_aws = aws_mut(&mut remote);
+        remote_aws.records[0].add_field("region".into(), "eu-north-1".into()).unwrap();
+        remote_aws.add_record(record(&[("login", "deploy")]));
+        remote.subfolders.as_mut().unwrap()[0].add_folder(Folder::new("gcp".into()));
+
+        let result = merge(remote, local);
+
+        assert!(result.conflicts.is_empty());
+        let infra = subfolder(&result.vault.root, "infra");
+        let aws = subfolder(infra, "aws");
+        assert_eq!(aws.records.len(), 2);
+        assert_eq!(aws.records[0].fields["region"].to_string(), "eu-north-1");
+        assert_eq!(aws.records[1].fields["login"].to_string(), "deploy");
+        subfolder(infra, "gcp");
+        subfolder(&result.vault.root, "local only");
+    }
+
+    #[test]
+    fn test_merge_keeps_unrelated_folders_with_same_name() {
+        let local = create_tree();
+        let remote = create_tree();
+
+        let result = merge(remote, local);
+
+        assert_eq!(result.vault.root.subfolders.unwrap().len(), 2);
+        assert!(result.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_merge_reports_nested_conflicts() {
+        let mut local = create_tree();
+        let mut remote = local.clone();
+        aws_mut(&mut local).records[0].fields.insert("password".into(), "local".into());
+        aws_mut(&mut remote).records[0].fields.insert("password".into(), "remote".into());
+        let record_id = aws_mut(&mut local).records[0].id();
+
+        let result = merge(remote, local);
+
+        assert_eq!(result.conflicts, vec![MergeConflict::Field {
+            path: vec!["infra".into(), "aws".into()],
+            record: record_id,
+            field: "password".into(),
+            local: "local".into(),
+            remote: "remote".into(),
+        }]);
+        let record = result.vault.root.find_record(record_id).unwrap();
+        assert_eq!(record.fields["password"].to_string(), "local");
+    }
+
+    #[test]
+    fn test_merge_prefers_last_writer() {
+        let mut local = create_tree();
+        let mut remote = local.clone();
+        aws_mut(&mut local).records[0].fields.insert("password".into(), "local".into());
+        let remote_record = &mut aws_mut(&mut remote).records[0];
+        remote_record.fields.insert("password".into(), "remote".into());
+        remote_record.modified += Duration::minutes(5);
+        let remote_modified = remote_record.modified();
+        let record_id = remote_record.id();
+
+        let result = merge(remote, local);
+
+        assert_eq!(result.conflicts.len(), 1);
+        let record = result.vault.root.find_record(record_id).unwrap();
+        assert_eq!(record.fields["password"].to_string(), "remote");
+        assert_eq!(record.modified(), remote_modified);
+    }
+
+    #[test]
+    fn test_merge_tags_and_favorite() {
+        let mut local = create_tree();
+        let mut remote = local.clone();
+        let local_record = &mut aws_mut(&mut local).records[0];
+        local_record.add_tag("prod");
+        local_record.set_favorite(true);
+        let remote_record = &mut aws_mut(&mut remote).records[0];
+        remote_record.add_tag("on-call");
+        remote_record.set_favorite(false);
+        remote_record.modified += Duration::minutes(5);
+        let record_id = remote_record.id();
+
+        let result = merge(remote, local);
+
+        let record = result.vault.root.find_record(record_id).unwrap();
+        assert_eq!(record.tags().iter().collect::<Vec<_>>(), ["on-call", "prod"]);
+        assert!(!record.is_favorite());
+    }
+
+    #[test]
+    fn test_merge_applies_deletions() {
+        let local = Vault::new(create_tree());
+        let mut remote = local.clone();
+        let record_id = aws_mut(&mut remote.root).records[0].id();
+        remote.trash_record(record_id).unwrap();
+
+        for result in [merge::merge(remote.clone(), local.clone()), merge::merge(local, remote)] {
+            assert!(result.vault.root.find_record(record_id).is_none());
+            assert_eq!(result.vault.trash().entries().len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_items_changed_after_deletion() {
+        let mut local = Vault::new(create_tree());
+        let mut remote = local.clone();
+        let record_id = aws_mut(&mut local.root).records[0].id();
+        local.trash_record(record_id).unwrap();
+        let remote_record = &mut aws_mut(&mut remote.root).records[0];
+        remote_record.fields.insert("password".into(), "rotated".into());
+        remote_record.modified = Utc::now() + Duration::minutes(5);
+
+        let result = merge::merge(remote, local.clone());
+        let record = result.vault.root.find_record(record_id).unwrap();
+        assert_eq!(record.fields["password"].to_string(), "rotated");
+        assert!(result.vault.trash().entries().is_empty());
+
+        let remote = local.clone();
+        local.restore_from_trash(0).unwrap();
+        let result = merge::merge(remote, local);
+        assert!(result.vault.root.find_record(record_id).is_some());
+        assert!(result.vault.trash().entries().is_empty());
+    }
+
+    #[test]
+    fn test_merge_trashes_the_merged_version() {
+        let mut local = Vault::new(create_tree());
+        let mut remote = local.clone();
+        let infra_id = local.root.subfolders.as_ref().unwrap()[0].id();
+        local.trash_folder(infra_id).unwrap();
+        aws_mut(&mut remote.root).add_record(record(&[("login", "deploy")]));
+
+        let result = merge::merge(remote, local);
+
+        assert!(result.vault.root.find_folder(infra_id).is_none());
+        let TrashedItem::Folder(infra) = &result.vault.trash().entries()[0].item else {
+            panic!("expected a trashed folder");
+        };
+        assert_eq!(infra.subfolders.as_ref().unwrap()[0].records.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_moves_records_to_the_newer_location() {
+        let local = create_tree();
+        let mut remote = local.clone();
+        let record_id = aws_mut(&mut remote).records[0].id();
+        remote.move_record(record_id, remote.id()).unwrap();
+
+        for mut result in [merge(remote.clone(), local.clone()), merge(local.clone(), remote.clone())] {
+            assert_eq!(result.vault.root.records.len(), 1);
+            assert_eq!(result.vault.root.records[0].id(), record_id);
+            assert!(aws_mut(&mut result.vault.root).records.is_empty());
+        }
+
+        let mut edited = local;
+        let edited_record = &mut aws_mut(&mut edited).records[0];
+        edited_record.fields.insert("password".into(), "rotated".into());
+        edited_record.modified = Utc::now() + Duration::minutes(5);
+        let mut result = merge(remote, edited);
+        assert!(result.vault.root.records.is_empty());
+        assert_eq!(aws_mut(&mut result.vault.root).records[0].fields["password"].to_string(), "rotated");
+    }
+
+    #[test]
+    fn test_merge_moves_folders() {
+        let mut local = create_tree();
+        local.add_folder(Folder::new("gcp".into()));
+        let mut remote = local.clone();
+        let infra_id = local.subfolders.as_ref().unwrap()[0].id();
+        let gcp_id = local.subfolders.as_ref().unwrap()[1].id();
+        let aws_id = aws_mut(&mut local).id();
+        remote.move_folder(aws_id, gcp_id).unwrap();
+
+        let result = merge(remote.clone(), local.clone());
+        assert!(subfolder(&result.vault.root, "infra").subfolders.is_none());
+        assert_eq!(subfolder(subfolder(&result.vault.root, "gcp"), "aws").records.len(), 1);
+
+        // Moved into each other on different sides, one of them goes back to the root.
+        let mut remote = local.clone();
+        local.move_folder(infra_id, gcp_id).unwrap();
+        remote.move_folder(gcp_id, infra_id).unwrap();
+        local.find_folder_mut(infra_id).unwrap().modified = Utc::now() + Duration::minutes(5);
+
+        let result = merge(remote, local);
+        let root = &result.vault.root;
+        assert_eq!(root.subfolders.as_ref().unwrap().len(), 1);
+        assert!(root.find_folder(infra_id).is_some());
+        assert!(root.find_folder(gcp_id).is_some());
+        assert_eq!(root.find_folder(aws_id).unwrap().records.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_templates_and_members() {
+        let mut base = Vault::new(create_tree());
+        base.add_member("Alice", "age1alice").unwrap();
+        base.add_member("Bob", "age1bob").unwrap();
+        base.add_template(RecordTemplate::new("VPN", &[("server", FieldKind::Url)])).unwrap();
+        let mut local = base.clone();
+        let mut remote = base;
+        remote.remove_member("age1bob").unwrap();
+        remote.add_member("Carol", "age1carol").unwrap();
+        local.remove_template("VPN").unwrap();
+        local.add_template(RecordTemplate::new("Router", &[])).unwrap();
+
+        for result in [merge::merge(remote.clone(), local.clone()), merge::merge(local.clone(), remote.clone())] {
+            let mut members: Vec<_> = result.vault.members().iter().map(|m| m.label.as_str()).collect();
+            members.sort();
+            assert_eq!(members, ["Alice", "Carol"]);
+            let templates: Vec<_> = result.vault.templates().iter().map(|t| t.name.as_str()).collect();
+            assert_eq!(templates, ["Router"]);
+        }
+
+        // Added again after the removals, which both sides still have.
+        let mut merged = merge::merge(remote.clone(), local.clone()).vault;
+        merged.add_member("Bob", "age1bob").unwrap();
+        merged.add_template(RecordTemplate::new("VPN", &[])).unwrap();
+        for result in [merge::merge(remote, merged.clone()), merge::merge(merged, local)] {
+            assert!(result.vault.members().iter().any(|m| m.label == "Bob"));
+            assert!(result.vault.find_template("VPN").unwrap().fields.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_merge_takes_one_sided_edits() {
+        let mut local = create_tree();
+        let mut remote = local.clone();
+        let remote_record = &mut aws_mut(&mut remote).records[0];
+        let mut fields = remote_record.fields.clone();
+        fields.insert("password".into(), "rotated".into());
+        remote_record.set_fields(fields);
+        let record_id = remote_record.id();
+        // Saved locally later without touching the password.
+        aws_mut(&mut local).records[0].modified = Utc::now() + Duration::minutes(5);
+
+        let result = merge(remote, local);
+
+        assert!(result.conflicts.is_empty());
+        let record = result.vault.root.find_record(record_id).unwrap();
+        assert_eq!(record.fields["password"].to_string(), "rotated");
+        assert_eq!(record.history().last().unwrap().fields["password"].to_string(), "initial");
+    }
+
+    #[test]
+    fn test_merge_keeps_losing_values_in_history() {
+        let mut local = create_tree();
+        let mut remote = local.clone();
+        aws_mut(&mut local).records[0].fields.insert("password".into(), "local".into());
+        let remote_record = &mut aws_mut(&mut remote).records[0];
+        remote_record.fields.insert("password".into(), "remote".into());
+        remote_record.modified += Duration::minutes(5);
+        let record_id = remote_record.id();
+
+        let result = merge(remote.clone(), local.clone());
+        let record = result.vault.root.find_record(record_id).unwrap();
+        assert_eq!(record.fields["password"].to_string(), "remote");
+        assert_eq!(record.history().last().unwrap().fields["password"].to_string(), "local");
+
+        let result = merge(local, remote);
+        let record = result.vault.root.find_record(record_id).unwrap();
+        assert_eq!(record.fields["password"].to_string(), "remote");
+        assert_eq!(record.history().last().unwrap().fields["password"].to_string(), "local");
+    }
+}
@@ -0,0 +1,115 @@
+//! Minimal in-process stand-in for the Telegram Bot API used by sync tests.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use url::Url;
+
+pub(crate) const TOKEN: &str = "123456:test-token";
+
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    pub method: String,
+    pub body: Vec<u8>,
+}
+
+#[derive(Default)]
+struct MockState {
+    results: HashMap<String, Value>,
+    files: HashMap<String, Vec<u8>>,
+    requests: Vec<MockRequest>,
+}
+
+pub(crate) struct MockBotApi {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockBotApi {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request)))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Self { addr, state }
+    }
+
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.addr)).unwrap()
+    }
+
+    /// Sets the `result` returned for every call of the Bot API `method`.
+    pub fn respond(&self, method: &str, result: Value) {
+        self.state.lock().unwrap().results.insert(method.to_lowercase(), result);
+    }
+
+    /// Serves `content` from the file download endpoint under `file_path`.
+    pub fn serve_file(&self, file_path: &str, content: Vec<u8>) {
+        self.state.lock().unwrap().files.insert(file_path.into(), content);
+    }
+
+    pub fn requests(&self, method: &str) -> Vec<MockRequest> {
+        let method = method.to_lowercase();
+        self.state.lock().unwrap().requests
+            .iter()
+            .filter(|r| r.method == method)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Message JSON as Telegram returns it for a document sent by the bot.
+pub(crate) fn document_message(message_id: i32, file_id: &str, file_unique_id: &str) -> Value {
+    json!({
+        "message_id": message_id,
+        "date": 0,
+        "chat": {"id": 42, "type": "private", "first_name": "nordstone"},
+        "document": {
+            "file_id": file_id,
+            "file_unique_id": file_unique_id,
+            "file_name": "nordstone.vault",
+            "file_size": 1,
+        },
+    })
+}
+
+async fn handle(state: Arc<Mutex<MockState>>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let path = request.uri().path().to_string();
+    let body = hyper::body::to_bytes(request.into_body()).await.unwrap().to_vec();
+    let mut state = state.lock().unwrap();
+
+    if let Some(file_path) = path.strip_prefix(&format!("/file/bot{}/", TOKEN)) {
+        return Ok(match state.files.get(file_path) {
+            Some(content) => Response::new(Body::from(content.clone())),
+            None => not_found(),
+        });
+    }
+    let method = match path.strip_prefix(&format!("/bot{}/", TOKEN)) {
+        Some(method) => method.to_lowercase(),
+        None => return Ok(not_found()),
+    };
+    state.requests.push(MockRequest { method: method.clone(), body });
+    let response = match state.results.get(&method) {
+        Some(result) => json!({"ok": true, "result": result}),
+        None => json!({"ok": false, "error_code": 400, "description": "Bad Request: method is not mocked"}),
+    };
+    Ok(Response::new(Body::from(response.to_string())))
+}
+
+fn not_found() -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response
+}
@@ -1,9 +1,21 @@
+mod errors;
+mod merge;
+#[cfg(test)]
+mod mock_bot;
 mod telegram;
 
-use crate::models::Folder;
+use crate::models::Vault;
 
+pub use errors::SyncError;
+pub use merge::{merge, MergeConflict, MergeResult};
+pub use telegram::{BotSettings, TelegramSyncManager, VaultDocument};
+
+#[allow(async_fn_in_trait)]
 pub trait SyncManager {
-    fn upload(&self, folder: Folder);
-    fn download(&self) -> Folder;
-    fn merge(&self, remote_data: Folder, local_folder: Folder) -> Folder;
-}
\ No newline at end of file
+    async fn upload(&mut self, vault: &mut Vault) -> Result<(), SyncError>;
+    async fn download(&self) -> Result<Vault, SyncError>;
+
+    fn merge(&self, remote_data: Vault, local_vault: Vault) -> MergeResult {
+        merge(remote_data, local_vault)
+    }
+}
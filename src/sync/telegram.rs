@@ -1,13 +1,15 @@
-use std::path::PathBuf;
-use std::fs;
-
 use serde::{Serialize, Deserialize};
+use teloxide::net::Download;
 use teloxide::prelude::*;
+use teloxide::types::{InputFile, MessageId, Recipient};
 use teloxide::utils::command::BotCommands;
+use url::Url;
 
-use crate::models::Folder;
-use crate::sync::SyncManager;
+use crate::encryption::{AgeEncryptor, Encryptor};
+use crate::models::Vault;
+use crate::sync::{SyncError, SyncManager};
 
+const VAULT_FILENAME: &str = "nordstone.vault";
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
@@ -30,38 +32,326 @@ async fn answer(bot: Bot, msg: Message, command: Command) -> ResponseResult<()>
     Ok(())
 }
 
-#[derive(Serialize, Deserialize)]
-struct BotSettings {
-    token: String,
-    chat_id: String,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BotSettings {
+    pub token: String,
+    pub chat_id: String,
+}
+
+impl BotSettings {
+    /// Numeric chat ids are used as is, anything else is treated as a channel username.
+    fn recipient(&self) -> Recipient {
+        match self.chat_id.parse::<i64>() {
+            Ok(id) => Recipient::Id(ChatId(id)),
+            Err(_) => Recipient::ChannelUsername(self.chat_id.clone()),
+        }
+    }
+}
+
+/// Telegram document holding the most recently uploaded vault.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VaultDocument {
+    pub message_id: i32,
+    pub file_id: String,
+    pub file_unique_id: String,
+}
+
+impl VaultDocument {
+    fn from_message(message: &Message) -> Option<Self> {
+        let file = &message.document()?.file;
+        Some(Self {
+            message_id: message.id.0,
+            file_id: file.id.clone(),
+            file_unique_id: file.unique_id.clone(),
+        })
+    }
 }
 
-struct TelegramSyncManager {
+pub struct TelegramSyncManager {
+    bot: Bot,
     bot_settings: BotSettings,
+    encryptor: AgeEncryptor,
+    vault_document: Option<VaultDocument>,
 }
 
 impl TelegramSyncManager {
-    fn new(bot_settings: BotSettings) -> Self
-    {
-        Self { bot_settings }
+    pub fn new(bot_settings: BotSettings, encryptor: AgeEncryptor) -> Self {
+        let bot = Bot::new(&bot_settings.token);
+        Self {
+            bot,
+            bot_settings,
+            encryptor,
+            vault_document: None,
+        }
+    }
+
+    /// Points the bot at a different Bot API server, e.g. a self-hosted one.
+    pub fn with_api_url(mut self, api_url: Url) -> Self {
+        self.bot = self.bot.set_api_url(api_url);
+        self
+    }
+
+    /// Starts from a vault document remembered from an earlier session.
+    pub fn with_vault_document(mut self, vault_document: VaultDocument) -> Self {
+        self.vault_document = Some(vault_document);
+        self
+    }
+
+    pub fn vault_document(&self) -> Option<&VaultDocument> {
+        self.vault_document.as_ref()
+    }
+
+    pub async fn configure_bot(&self) {
+        Command::repl(self.bot.clone(), answer).await;
+    }
+
+    /// Sends already encrypted vault bytes to the configured chat as a document
+    /// and pins it, so other machines can find the current vault through the chat.
+    /// The document is only remembered once it is pinned.
+    pub async fn upload_encrypted(&mut self, data: Vec<u8>) -> Result<&VaultDocument, SyncError> {
+        let document = InputFile::memory(data).file_name(VAULT_FILENAME);
+        let message = self.bot
+            .send_document(self.bot_settings.recipient(), document)
+            .await?;
+        let vault_document = VaultDocument::from_message(&message)
+            .ok_or(SyncError::MissingDocument)?;
+        self.bot
+            .pin_chat_message(self.bot_settings.recipient(), MessageId(vault_document.message_id))
+            .disable_notification(true)
+            .await?;
+        Ok(self.vault_document.insert(vault_document))
+    }
+
+    /// Finds the current vault document: the message pinned in the chat, which may
+    /// have been uploaded by another device, or else the one this manager uploaded.
+    /// A pinned document only counts if it is named like the vault, since anyone in
+    /// a shared chat can pin other files.
+    pub async fn locate_vault(&self) -> Result<VaultDocument, SyncError> {
+        let chat = self.bot.get_chat(self.bot_settings.recipient()).await?;
+        chat.pinned_message
+            .as_deref()
+            .filter(|message| {
+                message.document().and_then(|document| document.file_name.as_deref()) == Some(VAULT_FILENAME)
+            })
+            .and_then(VaultDocument::from_message)
+            .or_else(|| self.vault_document.clone())
+            .ok_or(SyncError::VaultNotFound)
     }
 
-    async fn configure_bot(&self) {
-        let bot = Bot::new(&self.bot_settings.token);
-        Command::repl(bot, answer).await;
+    /// Fetches the current vault document without decrypting it.
+    pub async fn download_encrypted(&self) -> Result<Vec<u8>, SyncError> {
+        let vault_document = self.locate_vault().await?;
+        let file = self.bot.get_file(vault_document.file_id).await?;
+        let mut encrypted_data = Vec::new();
+        self.bot.download_file(&file.path, &mut encrypted_data).await?;
+        Ok(encrypted_data)
     }
 }
 
 impl SyncManager for TelegramSyncManager {
-    fn upload(&self, folder: Folder) {
-        todo!()
+    async fn upload(&mut self, vault: &mut Vault) -> Result<(), SyncError> {
+        let encrypted_data = self.encryptor.encrypt(vault)?;
+        self.upload_encrypted(encrypted_data).await?;
+        Ok(())
+    }
+
+    async fn download(&self) -> Result<Vault, SyncError> {
+        let encrypted_data = self.download_encrypted().await?;
+        Ok(self.encryptor.decrypt(encrypted_data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::encryption::{AgeEncryptor, Encryptor};
+    use crate::models::{Folder, Record, Vault};
+    use crate::sync::mock_bot::{document_message, MockBotApi, TOKEN};
+    use crate::sync::{BotSettings, SyncError, SyncManager, TelegramSyncManager, VaultDocument};
+
+    fn create_manager(api: &MockBotApi) -> TelegramSyncManager {
+        let settings = BotSettings {
+            token: TOKEN.into(),
+            chat_id: "42".into(),
+        };
+        TelegramSyncManager::new(settings, AgeEncryptor::new("key".into()))
+            .with_api_url(api.url())
+    }
+
+    fn create_vault() -> Vault {
+        let mut folder = Folder::new("main".into());
+        let mut record = Record::new();
+        record.add_field("password".into(), "hunter2".into()).unwrap();
+        folder.add_record(record);
+        Vault::new(folder)
     }
 
-    fn download(&self) -> Folder {
-        todo!()
+    /// Serves an encrypted copy of `vault` as the Telegram file `file_id`.
+    fn serve_vault(api: &MockBotApi, file_id: &str, vault: &mut Vault) {
+        let encrypted = AgeEncryptor::new("key".into()).encrypt(vault).unwrap();
+        api.respond("getFile", json!({
+            "file_id": file_id,
+            "file_unique_id": "unique-id",
+            "file_size": encrypted.len(),
+            "file_path": "documents/file_0.vault",
+        }));
+        api.serve_file("documents/file_0.vault", encrypted);
     }
 
-    fn merge(&self, remote_data: Folder, local_folder: Folder) -> Folder {
-        todo!()
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[tokio::test]
+    async fn test_upload() {
+        let api = MockBotApi::start().await;
+        api.respond("sendDocument", document_message(7, "file-id", "unique-id"));
+        api.respond("pinChatMessage", json!(true));
+        let mut manager = create_manager(&api);
+        let mut vault = create_vault();
+
+        manager.upload(&mut vault).await.unwrap();
+
+        assert_eq!(manager.vault_document(), Some(&VaultDocument {
+            message_id: 7,
+            file_id: "file-id".into(),
+            file_unique_id: "unique-id".into(),
+        }));
+        let requests = api.requests("sendDocument");
+        assert_eq!(requests.len(), 1);
+        let body = &requests[0].body;
+        assert!(contains(body, b"name=\"chat_id\"\r\n\r\n42"));
+        assert!(contains(body, b"filename=\"nordstone.vault\""));
+        assert!(contains(body, b"age-encryption.org/v1"));
+        assert!(!contains(body, b"hunter2"));
+        let pins = api.requests("pinChatMessage");
+        assert_eq!(pins.len(), 1);
+        assert!(contains(&pins[0].body, b"\"message_id\":7"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_upload_without_document() {
+        let api = MockBotApi::start().await;
+        let mut message = document_message(7, "file-id", "unique-id");
+        message.as_object_mut().unwrap().remove("document");
+        message["text"] = "not a document".into();
+        api.respond("sendDocument", message);
+        let mut manager = create_manager(&api);
+
+        let result = manager.upload_encrypted(vec![1, 2, 3]).await;
+
+        assert!(result.is_err());
+        assert_eq!(manager.vault_document(), None);
+    }
+
+    #[tokio::test]
+    async fn test_upload_pin_error() {
+        let api = MockBotApi::start().await;
+        api.respond("sendDocument", document_message(7, "file-id", "unique-id"));
+        let mut manager = create_manager(&api);
+
+        let result = manager.upload_encrypted(vec![1, 2, 3]).await;
+
+        assert!(result.is_err());
+        assert_eq!(api.requests("pinChatMessage").len(), 1);
+        assert_eq!(manager.vault_document(), None);
+    }
+
+    #[tokio::test]
+    async fn test_upload_request_error() {
+        let api = MockBotApi::start().await;
+        let mut manager = create_manager(&api);
+
+        let result = manager.upload_encrypted(vec![1, 2, 3]).await;
+
+        assert!(result.is_err());
+    }
+
+    fn pinned_chat(message_id: i32, file_id: &str) -> serde_json::Value {
+        json!({
+            "id": 42,
+            "type": "private",
+            "first_name": "nordstone",
+            "pinned_message": document_message(message_id, file_id, "unique-id"),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_download_stored_document() {
+        let api = MockBotApi::start().await;
+        api.respond("getChat", json!({"id": 42, "type": "private", "first_name": "nordstone"}));
+        serve_vault(&api, "file-id", &mut create_vault());
+        let manager = create_manager(&api).with_vault_document(VaultDocument {
+            message_id: 7,
+            file_id: "file-id".into(),
+            file_unique_id: "unique-id".into(),
+        });
+
+        let vault = manager.download().await.unwrap();
+
+        assert_eq!(vault.root.name, "main");
+        assert_eq!(vault.root.records[0].fields["password"].to_string(), "hunter2");
+        assert_eq!(api.requests("getChat").len(), 1);
+        assert!(contains(&api.requests("getFile")[0].body, b"file-id"));
+    }
+
+    #[tokio::test]
+    async fn test_download_prefers_pinned_document() {
+        let api = MockBotApi::start().await;
+        api.respond("getChat", pinned_chat(9, "pinned-file-id"));
+        serve_vault(&api, "pinned-file-id", &mut create_vault());
+        let manager = create_manager(&api).with_vault_document(VaultDocument {
+            message_id: 7,
+            file_id: "stale-file-id".into(),
+            file_unique_id: "unique-id".into(),
+        });
+
+        manager.download().await.unwrap();
+
+        assert!(contains(&api.requests("getFile")[0].body, b"pinned-file-id"));
+    }
+
+    #[tokio::test]
+    async fn test_download_ignores_unrelated_pinned_document() {
+        let api = MockBotApi::start().await;
+        let mut chat = pinned_chat(9, "pinned-file-id");
+        chat["pinned_message"]["document"]["file_name"] = "minutes.pdf".into();
+        api.respond("getChat", chat);
+        serve_vault(&api, "file-id", &mut create_vault());
+        let manager = create_manager(&api).with_vault_document(VaultDocument {
+            message_id: 7,
+            file_id: "file-id".into(),
+            file_unique_id: "unique-id".into(),
+        });
+
+        let vault = manager.download().await.unwrap();
+
+        assert_eq!(vault.root.records[0].fields["password"].to_string(), "hunter2");
+        assert!(contains(&api.requests("getFile")[0].body, b"\"file-id\""));
+    }
+
+    #[tokio::test]
+    async fn test_download_pinned_document() {
+        let api = MockBotApi::start().await;
+        api.respond("getChat", pinned_chat(9, "pinned-file-id"));
+        serve_vault(&api, "pinned-file-id", &mut create_vault());
+        let manager = create_manager(&api);
+
+        let vault = manager.download().await.unwrap();
+
+        assert_eq!(vault.root.records[0].fields["password"].to_string(), "hunter2");
+        assert!(contains(&api.requests("getFile")[0].body, b"pinned-file-id"));
+    }
+
+    #[tokio::test]
+    async fn test_download_without_vault() {
+        let api = MockBotApi::start().await;
+        api.respond("getChat", json!({"id": 42, "type": "private", "first_name": "nordstone"}));
+        let manager = create_manager(&api);
+
+        let result = manager.download().await;
+
+        assert!(matches!(result, Err(SyncError::VaultNotFound)));
+    }
+}